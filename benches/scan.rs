@@ -0,0 +1,63 @@
+//! Scan throughput for joins with dense and sparse left sides.
+//!
+//! Run with `cargo bench --bench scan`. Reports the fastest of several runs
+//! for each case, in nanoseconds per match.
+use iejoins::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 10;
+
+/// Deterministic keys in `0..modulus`.
+fn keys(len: usize, seed: u64, modulus: u64) -> Vec<u64> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % modulus
+        })
+        .collect()
+}
+
+fn bench(name: &str, left_len: usize, right_len: usize) {
+    let modulus = 1 << 20;
+    let left1 = keys(left_len, 1, modulus);
+    let right1 = keys(right_len, 2, modulus);
+    let left2 = keys(left_len, 3, modulus);
+    let right2 = keys(right_len, 4, modulus);
+
+    let mut best = Duration::MAX;
+    let mut matches = 0;
+    for _ in 0..RUNS {
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+            JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone()),
+        )
+        .into_indices();
+
+        let start = Instant::now();
+        matches = black_box(join).count();
+        best = best.min(start.elapsed());
+    }
+
+    println!(
+        "{:<10} left={:<6} right={:<6} matches={:<10} {:>6.2} ns/match",
+        name,
+        left_len,
+        right_len,
+        matches,
+        best.as_nanos() as f64 / matches.max(1) as f64,
+    );
+}
+
+fn main() {
+    // Left rows set the scanned bits, so the share of left rows in the union
+    // is the bitvec's density.
+    const LEN: usize = 16_000;
+    for (num, den) in [(7, 8), (1, 2), (1, 4), (1, 8), (1, 16), (1, 32), (1, 128)] {
+        let left_len = LEN * num / den;
+        bench(&format!("{}/{}", num, den), left_len, LEN - left_len);
+    }
+}
@@ -1,8 +1,39 @@
 //! Inequality join.
 use bitvec::vec::BitVec;
-use std::fmt::Debug;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::ops::Range;
+use std::sync::Arc;
 
-#[derive(Debug)]
+pub mod btree;
+#[cfg(feature = "std")]
+pub mod channel;
+pub mod chunked;
+pub mod collapse;
+pub mod dynamic;
+pub mod hash;
+pub mod index;
+pub mod nullable;
+pub mod or;
+pub mod partition;
+pub mod prepared;
+pub mod round_robin;
+pub mod rows;
+pub mod shared;
+#[cfg(any(test, feature = "shuffle"))]
+pub mod shuffle;
+#[cfg(feature = "spill-sort")]
+pub mod spill_sort;
+pub mod star;
+pub mod summary;
+pub mod tagged;
+#[cfg(feature = "std")]
+pub mod timing;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CmpOp {
     Lt,   // <
     LtEq, // <=
@@ -13,10 +44,88 @@ pub enum CmpOp {
 impl CmpOp {
     fn sort_order(&self) -> SortOrder {
         match self {
-            CmpOp::Gt | CmpOp::LtEq => SortOrder::Desc,
-            _ => SortOrder::Asc,
+            CmpOp::Lt | CmpOp::LtEq => SortOrder::Asc,
+            CmpOp::Gt | CmpOp::GtEq => SortOrder::Desc,
+        }
+    }
+
+    /// Tie-break matching the operator's strictness.
+    ///
+    /// A left entry must be placed before an equal right entry for the pair
+    /// to match, so non-strict operators put left first.
+    fn tie_break(&self) -> TieBreak {
+        match self {
+            CmpOp::Lt | CmpOp::Gt => TieBreak::RightFirst,
+            CmpOp::LtEq | CmpOp::GtEq => TieBreak::LeftFirst,
+        }
+    }
+
+    /// Evaluate `left op right`.
+    fn holds<T: Ord>(&self, left: &T, right: &T) -> bool {
+        match self {
+            CmpOp::Lt => left < right,
+            CmpOp::LtEq => left <= right,
+            CmpOp::Gt => left > right,
+            CmpOp::GtEq => left >= right,
+        }
+    }
+}
+
+/// Which side sorts first when a left and right key are equal.
+///
+/// Equal keys only match if the left entry sorts first, so `LeftFirst` makes
+/// an operator behave as non-strict at the boundary and `RightFirst` as
+/// strict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreak {
+    LeftFirst,
+    RightFirst,
+}
+
+impl TieBreak {
+    fn first(&self) -> Side {
+        match self {
+            TieBreak::LeftFirst => Side::Left,
+            TieBreak::RightFirst => Side::Right,
+        }
+    }
+}
+
+/// How a union is sorted for a predicate.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct KeyOrder {
+    op: CmpOp,
+    tie: TieBreak,
+    /// Order both inputs are already sorted in, if any.
+    presorted: Option<SortOrder>,
+}
+
+impl KeyOrder {
+    /// Order for `op` using its default tie-break.
+    fn new(op: CmpOp) -> Self {
+        KeyOrder {
+            op,
+            tie: op.tie_break(),
+            presorted: None,
         }
     }
+
+    fn compare<T: Ord>(&self, (v1, s1): (&T, &Side), (v2, s2): (&T, &Side)) -> Ordering {
+        let ord = match self.op.sort_order() {
+            SortOrder::Asc => v1.cmp(v2),
+            SortOrder::Desc => v2.cmp(v1),
+        };
+        ord.then_with(|| {
+            let first = self.tie.first();
+            match (s1 == &first, s2 == &first) {
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => Ordering::Equal,
+            }
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -24,6 +133,7 @@ pub struct JoinPredicate<T> {
     op: CmpOp,
     left: Vec<T>,
     right: Vec<T>,
+    input_order: Option<SortOrder>,
 }
 
 impl<T> JoinPredicate<T>
@@ -31,16 +141,282 @@ where
     T: Ord,
 {
     pub fn new(op: CmpOp, left: Vec<T>, right: Vec<T>) -> Self {
-        JoinPredicate { op, left, right }
+        JoinPredicate {
+            op,
+            left,
+            right,
+            input_order: None,
+        }
+    }
+
+    /// Declare that both `left` and `right` are already sorted in `order`.
+    ///
+    /// The union is then built by merging the two sides, reversing them first
+    /// if the operator needs the opposite order, instead of sorting.
+    pub fn with_input_order(mut self, order: SortOrder) -> Self {
+        self.input_order = Some(order);
+        self
+    }
+
+    /// Replace every left key with `f(key)` before the union is sorted, e.g.
+    /// to join on `left + offset < right`.
+    ///
+    /// The join yields the transformed keys. With
+    /// [`JoinPredicate::with_input_order`], `f` must keep the keys sorted.
+    pub fn with_left_transform<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> T,
+    {
+        self.left = self.left.iter().map(f).collect();
+        self
+    }
+
+    /// Replace every right key with `f(key)` before the union is sorted, see
+    /// [`JoinPredicate::with_left_transform`].
+    pub fn with_right_transform<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> T,
+    {
+        self.right = self.right.iter().map(f).collect();
+        self
+    }
+
+    /// Hash of the operator and input lengths, ignoring the keys themselves,
+    /// for caching decisions made per join shape.
+    ///
+    /// Uses FNV-1a so the value is stable across runs and builds.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint(self.op, self.left.len(), self.right.len())
+    }
+
+    fn key_order(&self) -> KeyOrder {
+        KeyOrder {
+            presorted: self.input_order,
+            ..KeyOrder::new(self.op)
+        }
+    }
+}
+
+impl<'a, T> JoinPredicate<&'a T>
+where
+    T: Ord,
+{
+    /// Create a predicate over borrowed columns.
+    ///
+    /// The unions hold references into `left` and `right`, so the columns are
+    /// neither moved nor cloned. The join then yields references to keys.
+    pub fn from_slices(op: CmpOp, left: &'a [T], right: &'a [T]) -> Self {
+        Self::new(op, left.iter().collect(), right.iter().collect())
+    }
+}
+
+/// Whether an end of an interval is included, see
+/// [`InequalityJoin::interval`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundOp {
+    Open,
+    Closed,
+}
+
+impl BoundOp {
+    /// Operator comparing a point to the start of an interval.
+    fn start_op(&self) -> CmpOp {
+        match self {
+            BoundOp::Open => CmpOp::Gt,
+            BoundOp::Closed => CmpOp::GtEq,
+        }
+    }
+
+    /// Operator comparing a point to the end of an interval.
+    fn end_op(&self) -> CmpOp {
+        match self {
+            BoundOp::Open => CmpOp::Lt,
+            BoundOp::Closed => CmpOp::LtEq,
+        }
+    }
+}
+
+/// Points on the left and half-open ranges on the right, joined on
+/// containment by [`InequalityJoin::from_ranges`].
+#[derive(Debug)]
+pub struct RangeKey<T> {
+    points: Vec<T>,
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> RangeKey<T> {
+    pub fn new<I>(points: Vec<T>, ranges: I) -> Self
+    where
+        I: IntoIterator<Item = Range<T>>,
+    {
+        RangeKey {
+            points,
+            ranges: ranges.into_iter().collect(),
+        }
+    }
+}
+
+/// Which extreme to keep, see [`InequalityJoin::extremum_per_left`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtremumKind {
+    Max,
+    Min,
+}
+
+/// Which matching right an as-of join keeps, see [`InequalityJoin::asof`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsofDirection {
+    /// The right with the largest key in the first predicate, e.g. the latest
+    /// quote at or before a trade.
+    Backward,
+    /// The right with the smallest key in the first predicate.
+    Forward,
+}
+
+/// Both predicates evaluated on a single pair, see
+/// [`InequalityJoin::explain_pair`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairExplanation<'a, T1, T2> {
+    pub pred1: PredicateResult<'a, T1>,
+    pub pred2: PredicateResult<'a, T2>,
+}
+
+impl<T1, T2> PairExplanation<'_, T1, T2> {
+    /// Whether the pair satisfies both predicates.
+    pub fn matches(&self) -> bool {
+        self.pred1.holds && self.pred2.holds
+    }
+}
+
+/// A single predicate, `left op right`, evaluated on a pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PredicateResult<'a, T> {
+    pub op: CmpOp,
+    pub left: &'a T,
+    pub right: &'a T,
+    pub holds: bool,
+}
+
+impl<'a, T: Ord> PredicateResult<'a, T> {
+    fn new(op: CmpOp, left: &'a T, right: &'a T) -> Self {
+        PredicateResult {
+            op,
+            left,
+            right,
+            holds: op.holds(left, right),
+        }
+    }
+}
+
+/// Errors from constructing an invalid join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// The predicates have a different number of left rows.
+    LeftLengthMismatch { join1: usize, join2: usize },
+    /// The predicates have a different number of right rows.
+    RightLengthMismatch { join1: usize, join2: usize },
+    /// The combined number of left and right rows doesn't fit in a `usize`.
+    IndexOverflow { left: usize, right: usize },
+    /// A side has more rows than can be indexed by a `u32`.
+    PackedIndexOverflow { left: usize, right: usize },
+    /// Building the join would use more memory than allowed.
+    MemoryBudgetExceeded { estimated: usize, budget: usize },
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::LeftLengthMismatch { join1, join2 } => write!(
+                f,
+                "left length mismatch between predicates: {} != {}",
+                join1, join2
+            ),
+            JoinError::RightLengthMismatch { join1, join2 } => write!(
+                f,
+                "right length mismatch between predicates: {} != {}",
+                join1, join2
+            ),
+            JoinError::IndexOverflow { left, right } => write!(
+                f,
+                "union of {} left and {} right rows overflows usize",
+                left, right
+            ),
+            JoinError::PackedIndexOverflow { left, right } => write!(
+                f,
+                "{} left or {} right rows overflow u32 indices",
+                left, right
+            ),
+            JoinError::MemoryBudgetExceeded { estimated, budget } => write!(
+                f,
+                "join needs an estimated {} bytes, over the budget of {} bytes",
+                estimated, budget
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+fn validate<T1, T2>(join1: &JoinPredicate<T1>, join2: &JoinPredicate<T2>) -> Result<(), JoinError> {
+    validate_lens(
+        (join1.left.len(), join1.right.len()),
+        (join2.left.len(), join2.right.len()),
+    )
+}
+
+/// Check the `(left, right)` lengths of two predicates.
+fn validate_lens(
+    (left1, right1): (usize, usize),
+    (left2, right2): (usize, usize),
+) -> Result<(), JoinError> {
+    if left1 != left2 {
+        return Err(JoinError::LeftLengthMismatch {
+            join1: left1,
+            join2: left2,
+        });
+    }
+    if right1 != right2 {
+        return Err(JoinError::RightLengthMismatch {
+            join1: right1,
+            join2: right2,
+        });
+    }
+    check_union_len(left1, right1)
+}
+
+fn check_union_len(left: usize, right: usize) -> Result<(), JoinError> {
+    match left.checked_add(right) {
+        Some(_) => Ok(()),
+        None => Err(JoinError::IndexOverflow { left, right }),
+    }
+}
+
+fn check_packed_len(left: usize, right: usize, limit: usize) -> Result<(), JoinError> {
+    if left > limit || right > limit {
+        return Err(JoinError::PackedIndexOverflow { left, right });
     }
+    Ok(())
 }
 
+/// Join producing pairs where `left1 op1 right1` and `left2 op2 right2` both
+/// hold.
+///
+/// Each match is emitted as the left key of the first predicate along with the
+/// right key of the second predicate.
 #[derive(Debug)]
 pub struct InequalityJoin<T1, T2> {
     l1: L1Union<T1>,
     l2: L2Union<T2>,
+    left_len: usize,
+    /// Size of the right index space, including removed rows.
+    right_len: usize,
+    /// Right rows taken out by [`InequalityJoin::remove_right`].
+    removed_rights: usize,
 
     l2_idx: usize,
+    /// One past the last right entry in L2. Left entries after it only set
+    /// bits that no right entry will probe, so the scan stops here.
+    l2_end: usize,
 
     bitvec: L1BitVec,
 }
@@ -50,211 +426,3778 @@ where
     T1: Ord + Debug,
     T2: Ord + Debug,
 {
+    /// Create a join.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
     pub fn new(join1: JoinPredicate<T1>, join2: JoinPredicate<T2>) -> Self {
-        let (perms, l1) = L1Union::union_and_sort(join1.left, join1.right, join1.op.sort_order());
+        Self::try_new(join1, join2).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a join, checking that both predicates have the same number of
+    /// left and right rows and that the unions can be indexed.
+    pub fn try_new(join1: JoinPredicate<T1>, join2: JoinPredicate<T2>) -> Result<Self, JoinError> {
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        Self::build(join1, join2, order1, order2, JoinScratch::default())
+    }
+
+    /// Create a join, first checking that the memory it allocates is
+    /// estimated to fit in `max_bytes`.
+    ///
+    /// The estimate covers the unions, the permutation used to build L2, and
+    /// the scan's bitvec. Keys are counted by their inline size, so memory
+    /// they own on the heap isn't included.
+    pub fn try_new_with_budget(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        max_bytes: usize,
+    ) -> Result<Self, JoinError> {
+        validate(&join1, &join2)?;
+        let estimated = Self::estimate_bytes(join1.left.len() + join1.right.len());
+        if estimated > max_bytes {
+            return Err(JoinError::MemoryBudgetExceeded {
+                estimated,
+                budget: max_bytes,
+            });
+        }
+        Self::try_new(join1, join2)
+    }
+
+    /// Estimated bytes allocated to build and scan a join with `len` left and
+    /// right rows. Saturates at `usize::MAX`.
+    fn estimate_bytes(len: usize) -> usize {
+        let per_row = std::mem::size_of::<(T1, Side, usize)>()
+            + std::mem::size_of::<(T2, usize)>()
+            + std::mem::size_of::<usize>();
+        len.saturating_mul(per_row).saturating_add(len / 8 + 1)
+    }
+
+    /// Create a join reusing the scan allocations from a previous join, see
+    /// [`InequalityJoin::into_scratch`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
+    pub fn with_scratch(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        scratch: JoinScratch,
+    ) -> Self {
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        Self::build(join1, join2, order1, order2, scratch).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a join where equal left and right keys are ordered by `tie` in
+    /// both unions, instead of by each operator's strictness.
+    pub fn with_tie_break(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        tie: TieBreak,
+    ) -> Self {
+        Self::with_tie_breaks(join1, join2, tie, tie)
+    }
+
+    /// Create a join where equal left and right keys are ordered by `tie1` in
+    /// L1 and by `tie2` in L2.
+    pub fn with_tie_breaks(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        tie1: TieBreak,
+        tie2: TieBreak,
+    ) -> Self {
+        let order1 = KeyOrder {
+            tie: tie1,
+            ..join1.key_order()
+        };
+        let order2 = KeyOrder {
+            tie: tie2,
+            ..join2.key_order()
+        };
+        Self::build(join1, join2, order1, order2, JoinScratch::default())
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a join without checking the predicates.
+    ///
+    /// The caller must ensure the predicates are valid, see
+    /// [`InequalityJoin::try_new`]. Invalid predicates don't cause undefined
+    /// behavior, but the join may panic or yield incorrect matches.
+    pub fn new_unchecked(join1: JoinPredicate<T1>, join2: JoinPredicate<T2>) -> Self {
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        Self::build_unchecked(join1, join2, order1, order2, JoinScratch::default())
+    }
+
+    fn build(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        order1: KeyOrder,
+        order2: KeyOrder,
+        scratch: JoinScratch,
+    ) -> Result<Self, JoinError> {
+        validate(&join1, &join2)?;
+        Ok(Self::build_unchecked(join1, join2, order1, order2, scratch))
+    }
+
+    fn build_unchecked(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        order1: KeyOrder,
+        order2: KeyOrder,
+        scratch: JoinScratch,
+    ) -> Self {
+        let (l1, l2) = Self::build_unions(join1, join2, order1, order2);
+        Self::from_unions_with_scratch(l1, l2, scratch)
+    }
+
+    fn build_unions(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        order1: KeyOrder,
+        order2: KeyOrder,
+    ) -> (L1Union<T1>, L2Union<T2>) {
+        let left_len = join1.left.len();
+        let (perms, l1) = L1Union::union_and_sort(join1.left, join1.right, &order1);
         let l2 = L2Union::union_and_sort_with_permutations(
             join2.left,
             join2.right,
-            join2.op.sort_order(),
+            &order2,
+            left_len,
             perms,
         );
+        (l1, l2)
+    }
+
+    /// Build and sort the unions without allocating the scan's bitvec, to
+    /// inspect them before starting the join with [`InequalityJoin::start`].
+    pub fn prepare(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+    ) -> Result<PreparedJoin<T1, T2>, JoinError> {
+        validate(&join1, &join2)?;
+        let left_len = join1.left.len();
+        let right_len = join1.right.len();
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        let (l1, l2) = Self::build_unions(join1, join2, order1, order2);
+        Ok(PreparedJoin {
+            l1,
+            l2,
+            left_len,
+            right_len,
+        })
+    }
 
-        let bitvec = L1BitVec::new(l1.values.len());
+    /// Allocate the bitvec and start scanning prepared unions.
+    pub fn start(prepared: PreparedJoin<T1, T2>) -> Self {
+        Self::from_unions(prepared.l1, prepared.l2)
+    }
+
+    fn from_unions(l1: L1Union<T1>, l2: L2Union<T2>) -> Self {
+        Self::from_unions_with_scratch(l1, l2, JoinScratch::default())
+    }
+
+    fn from_unions_with_scratch(l1: L1Union<T1>, l2: L2Union<T2>, scratch: JoinScratch) -> Self {
+        let left_len = l1.values.iter().filter(|(_, s, _)| s.is_left()).count();
+        let right_len = l1.values.len() - left_len;
+        let mut bitvec = scratch.bitvec;
+        bitvec.reset_with_size(l1.values.len());
+        let l2_end = scan_end(&l1, &l2);
 
         InequalityJoin {
             l1,
             l2,
+            left_len,
+            right_len,
+            removed_rights: 0,
             l2_idx: 0,
+            l2_end,
             bitvec,
         }
     }
 }
 
-impl<T1, T2> Iterator for InequalityJoin<T1, T2>
+impl<T> InequalityJoin<T, T>
 where
-    T1: Ord + Clone + Debug,
-    T2: Ord + Clone + Debug,
+    T: Ord + Clone + Debug,
 {
-    type Item = (T1, T2);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let (v2, permuted) = match self.l2.values.get(self.l2_idx) {
-                Some((v, p)) => (v, *p),
-                None => return None,
-            };
-            self.bitvec.maybe_reset_start(permuted);
-
-            let (_, side, _) = self.l1.values.get(permuted).unwrap();
-            if side.is_right() {
-                self.l2_idx += 1;
-                self.bitvec.set(permuted);
-                continue;
-            }
+    /// Join points on the left with half-open intervals `[start, end)` on the
+    /// right, matching where `start <= point < end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `starts` and `ends` have different lengths.
+    pub fn half_open_interval(points: Vec<T>, starts: Vec<T>, ends: Vec<T>) -> Self {
+        Self::interval(points, starts, ends, BoundOp::Closed, BoundOp::Open)
+    }
 
-            if let Some(idx) = self.bitvec.next() {
-                let (v1, _, _) = self.l1.values.get(idx).unwrap();
-                return Some((v1.clone(), v2.clone()));
-            }
+    /// Join points on the left with intervals on the right, with each end of
+    /// the intervals open or closed. For example `Closed, Open` matches
+    /// `start <= point < end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `starts` and `ends` have different lengths.
+    pub fn interval(
+        points: Vec<T>,
+        starts: Vec<T>,
+        ends: Vec<T>,
+        start: BoundOp,
+        end: BoundOp,
+    ) -> Self {
+        Self::new(
+            JoinPredicate::new(start.start_op(), points.clone(), starts),
+            JoinPredicate::new(end.end_op(), points, ends),
+        )
+    }
 
-            self.l2_idx += 1;
-        }
+    /// Join points with the ranges containing them, see
+    /// [`InequalityJoin::half_open_interval`].
+    ///
+    /// Matches are yielded as the point along with the end of the range.
+    pub fn from_ranges(key: RangeKey<T>) -> Self {
+        let (starts, ends) = key.ranges.into_iter().map(|r| (r.start, r.end)).unzip();
+        Self::half_open_interval(key.points, starts, ends)
     }
 }
 
-#[derive(Debug)]
-struct L1BitVec {
-    bitvec: BitVec,
-    idx: usize,
-    start_idx: usize,
+impl<'a, T> InequalityJoin<&'a T, &'a T>
+where
+    T: Ord + Debug,
+{
+    /// Join on the conjunction of `left op right` for every `(op, left,
+    /// right)` predicate, yielding `(left_idx, right_idx)` matches.
+    ///
+    /// The first two predicates drive the join, the rest are checked against
+    /// each of its matches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two predicates, or if the predicates
+    /// have different numbers of left or right rows.
+    pub fn conjunction(
+        predicates: &[(CmpOp, &'a [T], &'a [T])],
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        assert!(
+            predicates.len() >= 2,
+            "conjunction needs at least two predicates"
+        );
+        let (op1, left1, right1) = predicates[0];
+        let (op2, left2, right2) = predicates[1];
+        let residuals = predicates[2..].to_vec();
+        for (_, left, right) in &residuals {
+            assert_eq!(left1.len(), left.len(), "left length mismatch");
+            assert_eq!(right1.len(), right.len(), "right length mismatch");
+        }
+
+        InequalityJoin::new(
+            JoinPredicate::from_slices(op1, left1, right1),
+            JoinPredicate::from_slices(op2, left2, right2),
+        )
+        .filter_pairs(move |l, r| {
+            residuals
+                .iter()
+                .all(|(op, left, right)| op.holds(&left[l], &right[r]))
+        })
+    }
 }
 
-impl L1BitVec {
-    fn new(size: usize) -> L1BitVec {
-        L1BitVec {
-            bitvec: BitVec::repeat(false, size),
-            idx: 0,
-            start_idx: 0,
+impl<T> InequalityJoin<T, T>
+where
+    T: Ord + Debug,
+{
+    /// Like [`InequalityJoin::conjunction`], for a number of predicates known
+    /// at compile time.
+    ///
+    /// The first two predicates drive the join. The rest stay in the array
+    /// and are checked against each of its matches, so the residual
+    /// predicates take no heap allocation of their own. Fails to compile if
+    /// `N` is less than two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`],
+    /// or have different numbers of left or right rows.
+    pub fn conjunction_array<const N: usize>(
+        predicates: [JoinPredicate<T>; N],
+    ) -> impl Iterator<Item = (usize, usize)> {
+        const { assert!(N >= 2, "conjunction needs at least two predicates") };
+        let mut predicates = predicates.into_iter();
+        let join1 = predicates.next().unwrap();
+        let join2 = predicates.next().unwrap();
+        for pred in predicates.as_slice() {
+            assert_eq!(join1.left.len(), pred.left.len(), "left length mismatch");
+            assert_eq!(join1.right.len(), pred.right.len(), "right length mismatch");
         }
+
+        InequalityJoin::new(join1, join2).filter_pairs(move |l, r| {
+            predicates
+                .as_slice()
+                .iter()
+                .all(|pred| pred.op.holds(&pred.left[l], &pred.right[r]))
+        })
     }
+}
 
-    fn maybe_reset_start(&mut self, start_idx: usize) {
-        if start_idx == self.start_idx {
-            return;
-        }
-        self.idx = start_idx;
-        self.start_idx = start_idx;
+impl<T> InequalityJoin<T, ()>
+where
+    T: Ord + Debug,
+{
+    /// Create a join on a single predicate, `left op right`.
+    ///
+    /// Without a second key, every left entry is visited before every right
+    /// entry in the L2 scan. That's the order of the unsorted union, so L2 is
+    /// built directly from the L1 permutation without sorting.
+    pub fn single(op: CmpOp, left: Vec<T>, right: Vec<T>) -> Self {
+        check_union_len(left.len(), right.len()).unwrap_or_else(|e| panic!("{}", e));
+
+        let join = JoinPredicate::new(op, left, right);
+        let order = join.key_order();
+        let (perms, l1) = L1Union::union_and_sort(join.left, join.right, &order);
+        let l2 = L2Union {
+            values: perms.into_iter().map(|p| ((), p)).collect(),
+            order: KeyOrder {
+                op: CmpOp::LtEq,
+                tie: TieBreak::LeftFirst,
+                presorted: None,
+            },
+        };
+
+        Self::from_unions(l1, l2)
     }
 
-    fn set(&mut self, idx: usize) {
-        self.bitvec.set(idx, true);
+    /// Join `values` with itself on `a op b`, yielding each matching
+    /// `(a_idx, b_idx)` only where `a_idx < b_idx`.
+    ///
+    /// Pairs on the diagonal and below are dropped, so a pair of rows is
+    /// emitted at most once even if it matches in both directions.
+    pub fn self_join_unique(op: CmpOp, values: Vec<T>) -> impl Iterator<Item = (usize, usize)>
+    where
+        T: Clone,
+    {
+        Self::single(op, values.clone(), values)
+            .into_indices()
+            .filter(|(left_idx, right_idx)| left_idx < right_idx)
     }
 }
 
-impl Iterator for L1BitVec {
-    type Item = usize;
+impl<T1, T2> InequalityJoin<T1, T2> {
+    /// Advance the scan to the next match, returning the L1 position of the
+    /// left entry and the L2 position of the right entry.
+    fn next_match(&mut self) -> Option<(usize, usize)> {
+        scan_next(
+            &self.l1,
+            &self.l2,
+            &mut self.l2_idx,
+            self.l2_end,
+            &mut self.bitvec,
+        )
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        while let Some(set) = self.bitvec.get(self.idx) {
-            if *set {
-                let idx = self.idx;
-                self.idx += 1;
-                return Some(idx);
+    /// Count the matches the scan has yet to produce, without advancing it.
+    ///
+    /// Runs the rest of the scan on a copy of the bitvec, counting the set
+    /// bits for each right entry instead of visiting them.
+    fn count_remaining(&self) -> usize {
+        let mut bits = self.bitvec.clone();
+        let mut count = 0;
+        let mut l2_idx = self.l2_idx;
+
+        // Finish the right entry the scan is part way through.
+        if let Some(&(_, permuted)) = self.l2.values[..self.l2_end].get(l2_idx) {
+            if !self.l1.values[permuted].1.is_left() && bits.end_idx == permuted {
+                let start = bits.idx.min(permuted);
+                count += bits.count_set_in_range(permuted) - bits.count_set_in_range(start);
+                l2_idx += 1;
             }
-            self.idx += 1;
         }
-        None
-    }
-}
 
-enum SortOrder {
-    Asc,
-    Desc,
+        for &(_, permuted) in &self.l2.values[l2_idx..self.l2_end] {
+            if self.l1.values[permuted].1.is_left() {
+                bits.set(permuted);
+            } else {
+                count += bits.count_set_in_range(permuted);
+            }
+        }
+        count
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum Side {
-    Left,
-    Right,
-}
+impl<T1, T2> Iterator for InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    type Item = (T1, T2);
 
-impl Side {
-    fn is_right(&self) -> bool {
-        matches!(self, Side::Right)
+    fn next(&mut self) -> Option<Self::Item> {
+        let (l1_idx, l2_idx) = self.next_match()?;
+        let (v1, _, _) = self.l1.values.get(l1_idx).unwrap();
+        let (v2, _) = self.l2.values.get(l2_idx).unwrap();
+        Some((v1.clone(), v2.clone()))
     }
 }
 
-#[derive(Debug)]
-struct L1Union<T> {
-    values: Vec<(T, Side, usize)>,
-}
-
-impl<T> L1Union<T>
+impl<T1, T2> InequalityJoin<T1, T2>
 where
-    T: Ord,
+    T1: Ord,
+    T2: Ord,
 {
-    fn union_and_sort(left: Vec<T>, right: Vec<T>, order: SortOrder) -> (Vec<usize>, Self) {
-        let left = left
-            .into_iter()
-            .enumerate()
-            .map(|(idx, v)| (v, Side::Left, idx));
-        let right = right
+    /// Find the left rows where `left1 op1 c1` and `left2 op2 c2`, returning
+    /// their input positions in ascending order.
+    ///
+    /// This is a join against a single right row, which only needs a pass
+    /// over the left rows rather than sorting and scanning unions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `left1` and `left2` have different lengths.
+    pub fn against_scalar(
+        op1: CmpOp,
+        op2: CmpOp,
+        left1: Vec<T1>,
+        left2: Vec<T2>,
+        c1: T1,
+        c2: T2,
+    ) -> impl Iterator<Item = usize> {
+        assert_eq!(left1.len(), left2.len(), "left length mismatch");
+        left1
             .into_iter()
+            .zip(left2)
             .enumerate()
-            .map(|(idx, v)| (v, Side::Right, idx));
+            .filter(move |(_, (k1, k2))| op1.holds(k1, &c1) && op2.holds(k2, &c2))
+            .map(|(idx, _)| idx)
+    }
 
-        let mut values: Vec<_> = left.chain(right).collect();
-        let mut idxs: Vec<_> = (0..values.len()).collect();
+    /// Find the left rows matching a single right row with keys `key1` and
+    /// `key2`, returning their input positions in ascending order.
+    ///
+    /// Only reads the sorted unions, so a join can be built once and probed
+    /// many times. The join's own right rows are ignored.
+    pub fn probe(&self, key1: T1, key2: T2) -> Vec<usize> {
+        // Left entries sorting before the probe key in a union satisfy that
+        // union's predicate.
+        let l1_end = self.l1.values.partition_point(|(v, side, _)| {
+            self.l1.order.compare((v, side), (&key1, &Side::Right)) == Ordering::Less
+        });
+        let l2_end = self.l2.values.partition_point(|(v, permuted)| {
+            let (_, side, _) = &self.l1.values[*permuted];
+            self.l2.order.compare((v, side), (&key2, &Side::Right)) == Ordering::Less
+        });
 
-        match order {
-            SortOrder::Asc => {
-                idxs.sort_unstable_by_key(|&idx| &values[idx].0);
-                values.sort_unstable_by(|(v1, _, _), (v2, _, _)| v1.cmp(v2));
-            }
-            SortOrder::Desc => {
-                idxs.sort_unstable_by_key(|&idx| &values[idx].0);
-                idxs.reverse();
-                values.sort_unstable_by(|(v1, _, _), (v2, _, _)| v2.cmp(v1));
+        let mut lefts: Vec<_> = self.l2.values[..l2_end]
+            .iter()
+            .filter_map(|(_, permuted)| match &self.l1.values[*permuted] {
+                (_, Side::Left, idx) if *permuted < l1_end => Some(*idx),
+                _ => None,
+            })
+            .collect();
+        lefts.sort_unstable();
+        lefts
+    }
+
+    /// Evaluate both predicates for the left row `left_idx` and right row
+    /// `right_idx`, reporting the compared keys.
+    ///
+    /// Keys are found by searching the unions, so this is linear in the size
+    /// of the join. Returns `None` if either row isn't in the join.
+    pub fn explain_pair(
+        &self,
+        left_idx: usize,
+        right_idx: usize,
+    ) -> Option<PairExplanation<'_, T1, T2>> {
+        let find = |side: Side, idx: usize| {
+            let pos = self
+                .l1
+                .values
+                .iter()
+                .position(|(_, s, i)| *s == side && *i == idx)?;
+            let (key2, _) = self.l2.values.iter().find(|(_, p)| *p == pos)?;
+            Some((&self.l1.values[pos].0, key2))
+        };
+        let (left1, left2) = find(Side::Left, left_idx)?;
+        let (right1, right2) = find(Side::Right, right_idx)?;
+
+        Some(PairExplanation {
+            pred1: PredicateResult::new(self.l1.order.op, left1, right1),
+            pred2: PredicateResult::new(self.l2.order.op, left2, right2),
+        })
+    }
+
+    /// Remove right row `right_idx` from the join, returning the matches it
+    /// had as `(left_idx, right_idx)` pairs in ascending left order.
+    ///
+    /// The remaining rows keep their input positions, so results indexed by
+    /// right position, like [`InequalityJoin::adjacency`], still span every
+    /// input row. Removing a row that isn't in the join returns no matches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scan has already started.
+    pub fn remove_right(&mut self, right_idx: usize) -> Vec<(usize, usize)>
+    where
+        T1: Clone,
+        T2: Clone,
+    {
+        assert_eq!(
+            0, self.l2_idx,
+            "can't remove rows once the scan has started"
+        );
+
+        let pos = match self
+            .l1
+            .values
+            .iter()
+            .position(|(_, side, idx)| !side.is_left() && *idx == right_idx)
+        {
+            Some(pos) => pos,
+            None => return Vec::new(),
+        };
+        let l2_pos = self
+            .l2
+            .values
+            .iter()
+            .position(|(_, permuted)| *permuted == pos)
+            .unwrap();
+
+        let key1 = self.l1.values[pos].0.clone();
+        let key2 = self.l2.values[l2_pos].0.clone();
+        let removed = self
+            .probe(key1, key2)
+            .into_iter()
+            .map(|left_idx| (left_idx, right_idx))
+            .collect();
+
+        self.l1.values.remove(pos);
+        self.l2.values.remove(l2_pos);
+        for (_, permuted) in self.l2.values.iter_mut() {
+            if *permuted > pos {
+                *permuted -= 1;
             }
         }
+        self.removed_rights += 1;
+        self.bitvec.reset_with_size(self.l1.values.len());
+        self.l2_end = scan_end(&self.l1, &self.l2);
 
-        (idxs, L1Union { values })
+        removed
     }
 }
 
-#[derive(Debug)]
-struct L2Union<T> {
-    values: Vec<(T, usize)>,
-}
+impl<T1, T2> InequalityJoin<T1, T2> {
+    /// Iterate over matches as `(left_idx, right_idx)` positions in the input
+    /// vectors instead of keys.
+    pub fn into_indices(self) -> InequalityJoinIndices<T1, T2> {
+        InequalityJoinIndices { join: self }
+    }
 
-impl<T> L2Union<T>
-where
-    T: Ord,
-{
-    fn union_and_sort_with_permutations(
-        left: Vec<T>,
-        right: Vec<T>,
-        order: SortOrder,
-        permutations: Vec<usize>,
-    ) -> Self {
-        let left = left.into_iter();
-        let right = right.into_iter();
+    /// Left outer join as `(left_idx, right_idx)` positions, with unmatched
+    /// left rows yielded as `(left_idx, None)`.
+    ///
+    /// Matches are yielded as the scan finds them. Unmatched left rows are
+    /// only known once the scan is done, and follow in input order.
+    pub fn left_outer(self) -> LeftOuterJoin<T1, T2> {
+        LeftOuterJoin {
+            matched: BitVec::repeat(false, self.left_len),
+            join: self,
+            drain_idx: None,
+        }
+    }
 
-        let mut permutations: Vec<_> = permutations.into_iter().enumerate().collect();
+    /// Run the join, returning a mask of `left_len` bits with bit `i` set if
+    /// left row `i` matched any right row.
+    pub fn matched_mask(self) -> BitVec {
+        let mut outer = self.left_outer();
+        // Matches all come before the first unmatched row.
+        while let Some((_, Some(_))) = outer.next() {}
+        outer.matched
+    }
 
-        let mut values: Vec<_> = left
-            .chain(right)
-            .enumerate()
-            .map(|(idx, v)| (v, idx))
-            .collect();
-        match order {
-            SortOrder::Asc => {
-                permutations.sort_unstable_by_key(|&(idx, _)| &values[idx].0);
-                values.sort_unstable_by(|(v1, _), (v2, _)| v1.cmp(v2));
-            }
-            SortOrder::Desc => {
-                permutations.sort_unstable_by_key(|&(idx, _)| &values[idx].0);
-                permutations.reverse();
-                values.sort_unstable_by(|(v1, _), (v2, _)| v2.cmp(v1));
+    /// Run the join, returning one row of `right_len` bits per left row with
+    /// bit `j` set if the left matches right row `j`.
+    ///
+    /// Rows are laid out like [`NestedLoopJoin::match_matrix`](crate::nlj::NestedLoopJoin::match_matrix).
+    pub fn adjacency(self) -> Vec<BitVec> {
+        let mut rows = vec![BitVec::repeat(false, self.right_len); self.left_len];
+        for (left_idx, right_idx) in self.into_indices() {
+            rows[left_idx].set(right_idx, true);
+        }
+        rows
+    }
+
+    /// Run the join, returning the `(left_idx, right_idx)` matches along with
+    /// the left rows without any match, in input order.
+    ///
+    /// This shadows [`Iterator::partition`], which can still be called as
+    /// `Iterator::partition(join, f)`.
+    pub fn partition(self) -> (Vec<(usize, usize)>, Vec<usize>) {
+        let mut matches = Vec::new();
+        let mut unmatched = Vec::new();
+        for (left_idx, right_idx) in self.left_outer() {
+            match right_idx {
+                Some(right_idx) => matches.push((left_idx, right_idx)),
+                None => unmatched.push(left_idx),
             }
         }
+        (matches, unmatched)
+    }
 
-        L2Union { values }
+    /// Collect matches as `(left_idx << 32) | right_idx`.
+    ///
+    /// Errors if either side has more than `u32::MAX` rows.
+    pub fn collect_packed(self) -> Result<Vec<u64>, JoinError> {
+        check_packed_len(self.left_len, self.right_len, u32::MAX as usize)?;
+        Ok(self
+            .into_indices()
+            .map(|(l, r)| ((l as u64) << 32) | r as u64)
+            .collect())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Collect matches as separate left and right index arrays.
+    ///
+    /// Errors if either side has more than `u32::MAX` rows.
+    pub fn collect_index_arrays(self) -> Result<(Vec<u32>, Vec<u32>), JoinError> {
+        check_packed_len(self.left_len, self.right_len, u32::MAX as usize)?;
+        Ok(self
+            .into_indices()
+            .map(|(l, r)| (l as u32, r as u32))
+            .unzip())
+    }
 
-    #[test]
-    fn simple() {
-        let join1 = JoinPredicate::new(CmpOp::Gt, vec![100, 140, 80, 90], vec![100, 140, 80, 90]);
-        let join2 = JoinPredicate::new(CmpOp::Lt, vec![6, 11, 10, 5], vec![6, 11, 10, 5]);
+    /// Collect matches into left and right index vectors, allocated to the
+    /// exact number of matches up front by a counting pass over the scan.
+    pub fn collect_indices_presized(self) -> (Vec<usize>, Vec<usize>) {
+        let len = self.count_remaining();
+        let mut left = Vec::with_capacity(len);
+        let mut right = Vec::with_capacity(len);
+        self.for_each_match(|l, r| {
+            left.push(l);
+            right.push(r);
+        });
+        (left, right)
+    }
+
+    /// Iterate over `(left_idx, right_idx)` pairs, guaranteeing each pair is
+    /// yielded once.
+    ///
+    /// The scan visits each right entry once, at increasing L2 positions, and
+    /// yields its left entries at increasing L1 positions. A match that doesn't
+    /// advance that order would be a repeat, and is dropped without tracking
+    /// the pairs already seen.
+    pub fn dedup_by_index(mut self) -> impl Iterator<Item = (usize, usize)> {
+        let mut last: Option<(usize, usize)> = None;
+        std::iter::from_fn(move || loop {
+            let (l1_idx, l2_idx) = self.next_match()?;
+            let advanced = match last {
+                Some((last_l1, last_l2)) => {
+                    l2_idx > last_l2 || (l2_idx == last_l2 && l1_idx > last_l1)
+                }
+                None => true,
+            };
+            if advanced {
+                last = Some((l1_idx, l2_idx));
+                return Some(self.match_indices((l1_idx, l2_idx)));
+            }
+        })
+    }
+
+    /// Iterate over matches as `(left_idx, right_idx, left_rank, right_rank)`,
+    /// where `left_rank` is the position of the left key in the sorted L1
+    /// union and `right_rank` the position of the right key in the sorted L2
+    /// union.
+    pub fn into_ranked(mut self) -> impl Iterator<Item = (usize, usize, usize, usize)> {
+        std::iter::from_fn(move || {
+            let (l1_idx, l2_idx) = self.next_match()?;
+            let (left_idx, right_idx) = self.match_indices((l1_idx, l2_idx));
+            Some((left_idx, right_idx, l1_idx, l2_idx))
+        })
+    }
+
+    /// Iterate over matches as `(ordinal, left_idx, right_idx)`, where
+    /// `ordinal` is the position of the match in the output, starting at 0.
+    ///
+    /// Ordinals follow the scan order, so a join over the same inputs always
+    /// numbers its matches the same way.
+    pub fn into_ordinals(self) -> impl Iterator<Item = (usize, usize, usize)> {
+        self.into_indices()
+            .enumerate()
+            .map(|(ordinal, (left_idx, right_idx))| (ordinal, left_idx, right_idx))
+    }
+
+    /// Iterate over `(left_idx, right_idx)` matches that also satisfy
+    /// `residual`, for predicates that can't be expressed as inequalities.
+    pub fn filter_pairs<F>(self, residual: F) -> impl Iterator<Item = (usize, usize)>
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        self.into_indices()
+            .filter(move |&(left_idx, right_idx)| residual(left_idx, right_idx))
+    }
+
+    /// Collect `(left_idx, right_idx)` matches sorted by left then right
+    /// index, independent of the scan order.
+    pub fn collect_sorted(self) -> Vec<(usize, usize)> {
+        let mut out: Vec<_> = self.into_indices().collect();
+        out.sort_unstable();
+        out
+    }
+
+    /// Collect matches as `(left_idx, right_range)`, merging the matches of a
+    /// left with consecutive right indices into one range.
+    ///
+    /// Ranges are sorted by left index, then by the start of the range.
+    pub fn collect_ranges(self) -> Vec<(usize, Range<usize>)> {
+        let mut out: Vec<(usize, Range<usize>)> = Vec::new();
+        for (left_idx, right_idx) in self.collect_sorted() {
+            match out.last_mut() {
+                Some((left, range)) if *left == left_idx && range.end == right_idx => {
+                    range.end += 1;
+                }
+                _ => out.push((left_idx, right_idx..right_idx + 1)),
+            }
+        }
+        out
+    }
+
+    /// Collect `(left_idx, right_idx)` matches sorted by `cmp`.
+    ///
+    /// All matches are buffered before sorting. The sort is stable, so
+    /// matches comparing equal keep their scan order.
+    pub fn collect_sorted_by<F>(self, cmp: F) -> Vec<(usize, usize)>
+    where
+        F: FnMut(&(usize, usize), &(usize, usize)) -> Ordering,
+    {
+        let mut out: Vec<_> = self.into_indices().collect();
+        out.sort_by(cmp);
+        out
+    }
+
+    /// Collect `(left_idx, right_idx)` matches sorted by the left key of the
+    /// first predicate, then the right key of the second predicate, with
+    /// pairs tied on both keys ordered by `left_ties` then `right_ties`.
+    ///
+    /// The tiebreak keys are indexed by input position and only affect the
+    /// output order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there isn't a tiebreak key for every left and right row.
+    pub fn collect_sorted_with_ties<K1, K2>(
+        mut self,
+        left_ties: &[K1],
+        right_ties: &[K2],
+    ) -> Vec<(usize, usize)>
+    where
+        T1: Ord,
+        T2: Ord,
+        K1: Ord,
+        K2: Ord,
+    {
+        assert_eq!(self.left_len, left_ties.len(), "left tiebreak length");
+        assert_eq!(self.right_len, right_ties.len(), "right tiebreak length");
+
+        let mut matches = Vec::new();
+        while let Some(m) = self.next_match() {
+            matches.push((m, self.match_indices(m)));
+        }
+        matches.sort_by(
+            |((l1_a, l2_a), (left_a, right_a)), ((l1_b, l2_b), (left_b, right_b))| {
+                self.l1.values[*l1_a]
+                    .0
+                    .cmp(&self.l1.values[*l1_b].0)
+                    .then_with(|| self.l2.values[*l2_a].0.cmp(&self.l2.values[*l2_b].0))
+                    .then_with(|| left_ties[*left_a].cmp(&left_ties[*left_b]))
+                    .then_with(|| right_ties[*right_a].cmp(&right_ties[*right_b]))
+            },
+        );
+        matches.into_iter().map(|(_, indices)| indices).collect()
+    }
+
+    /// Split the scan at L2 position `mid` into two ranges that can be
+    /// scanned independently, e.g. on separate threads.
+    ///
+    /// The second range starts with the bits set by every left entry before
+    /// `mid`, so concatenating the ranges' matches gives the matches of
+    /// [`InequalityJoin::into_indices`]. Both ranges share the unions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scan has already started.
+    pub fn split_at(self, mid: usize) -> (ScanRange<T1, T2>, ScanRange<T1, T2>) {
+        assert_eq!(0, self.l2_idx, "can't split once the scan has started");
+        let mid = mid.min(self.l2_end);
+
+        let first_bits = self.bitvec;
+        let mut second_bits = first_bits.clone();
+        for &(_, permuted) in &self.l2.values[..mid] {
+            if self.l1.values[permuted].1.is_left() {
+                second_bits.set(permuted);
+            }
+        }
+
+        let unions = Arc::new((self.l1, self.l2));
+        let first = ScanRange {
+            unions: unions.clone(),
+            l2_idx: 0,
+            l2_end: mid,
+            bitvec: first_bits,
+        };
+        let second = ScanRange {
+            unions,
+            l2_idx: mid,
+            l2_end: self.l2_end,
+            bitvec: second_bits,
+        };
+        (first, second)
+    }
+
+    /// Anti join yielding the input positions of left rows without any
+    /// match, each as soon as the scan proves it can't match.
+    ///
+    /// A left entry can only be matched by a later right entry in L2 with a
+    /// higher L1 position. Once none of the remaining right entries reach past
+    /// it, an unmatched left entry is yielded without scanning further.
+    pub fn unmatched_lefts(self) -> UnmatchedLefts<T1, T2> {
+        UnmatchedLefts::new(Arc::new((self.l1, self.l2)))
+    }
+
+    /// Collect matches in compressed sparse row form, returning `(offsets,
+    /// rights)`.
+    ///
+    /// The matched right positions of left row `i` are
+    /// `rights[offsets[i]..offsets[i + 1]]`, in ascending order. `offsets` has
+    /// `left_len + 1` entries.
+    pub fn to_csr(self) -> (Vec<usize>, Vec<usize>) {
+        let mut offsets = vec![0; self.left_len + 1];
+        let matches: Vec<_> = self.into_indices().collect();
+        for &(left_idx, _) in &matches {
+            offsets[left_idx + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+
+        let mut next = offsets.clone();
+        let mut rights = vec![0; matches.len()];
+        for (left_idx, right_idx) in matches {
+            rights[next[left_idx]] = right_idx;
+            next[left_idx] += 1;
+        }
+        for row in offsets.windows(2) {
+            rights[row[0]..row[1]].sort_unstable();
+        }
+
+        (offsets, rights)
+    }
+
+    /// Call `sink` with the `(left_idx, right_idx)` of every match, in the
+    /// same order as [`InequalityJoin::into_indices`].
+    pub fn for_each_match(mut self, mut sink: impl FnMut(usize, usize)) {
+        while let Some(m) = self.next_match() {
+            let (left_idx, right_idx) = self.match_indices(m);
+            sink(left_idx, right_idx);
+        }
+    }
+
+    /// Iterate over `(left_idx, right_idx)` pairs, pairing each matched right
+    /// row only with its matching left row of lowest input index.
+    ///
+    /// Right rows are yielded in scan order. A right row's matches are all
+    /// produced before the scan moves on, so this doesn't buffer.
+    pub fn first_left_per_right(mut self) -> impl Iterator<Item = (usize, usize)> {
+        let mut pending = self.next_match();
+        std::iter::from_fn(move || {
+            let (l1_idx, l2_idx) = pending?;
+            let (mut first, right_idx) = self.match_indices((l1_idx, l2_idx));
+            loop {
+                pending = self.next_match();
+                match pending {
+                    Some((l1_idx, next)) if next == l2_idx => {
+                        let (_, _, left_idx) = self.l1.values[l1_idx];
+                        first = first.min(left_idx);
+                    }
+                    _ => return Some((first, right_idx)),
+                }
+            }
+        })
+    }
+
+    /// Release the join's scan allocations for reuse with
+    /// [`InequalityJoin::with_scratch`].
+    pub fn into_scratch(self) -> JoinScratch {
+        JoinScratch {
+            bitvec: self.bitvec,
+        }
+    }
+
+    /// Save the scan's progress, to continue it later with
+    /// [`InequalityJoin::resume_from`].
+    pub fn snapshot(&self) -> ScanSnapshot {
+        ScanSnapshot {
+            unions_id: self.unions_id(),
+            l2_idx: self.l2_idx,
+            bitvec: self.bitvec.clone(),
+        }
+    }
+
+    /// Continue the scan from `snapshot`, taken from a join built from the
+    /// same inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshot was taken from a join with different operators,
+    /// numbers of rows, or scan end. Other differences in the keys aren't
+    /// detected.
+    pub fn resume_from(mut self, snapshot: ScanSnapshot) -> Self {
+        assert_eq!(
+            self.unions_id(),
+            snapshot.unions_id,
+            "snapshot is from a different join"
+        );
+        self.l2_idx = snapshot.l2_idx;
+        self.bitvec = snapshot.bitvec;
+        self
+    }
+
+    /// Fingerprints of both predicates along with the scan end, to tell the
+    /// unions of different joins apart, or of one join before and after a
+    /// row is removed.
+    fn unions_id(&self) -> (u64, u64, usize) {
+        (
+            fingerprint(self.l1.order.op, self.left_len, self.right_len()),
+            fingerprint(self.l2.order.op, self.left_len, self.right_len()),
+            self.l2_end,
+        )
+    }
+
+    /// Number of left rows.
+    pub fn left_len(&self) -> usize {
+        self.left_len
+    }
+
+    /// Number of right rows, not counting removed ones.
+    pub fn right_len(&self) -> usize {
+        self.right_len - self.removed_rights
+    }
+
+    /// Number of left and right row pairs, the size of the cross product.
+    /// Saturates at `usize::MAX`.
+    pub fn cartesian_size(&self) -> usize {
+        self.left_len.saturating_mul(self.right_len())
+    }
+
+    /// Input positions of the left rows in the order the first predicate
+    /// sorted them, so `left1[perm[i]]` is the `i`th smallest left key for
+    /// ascending operators.
+    pub fn left_permutation(&self) -> Vec<usize> {
+        self.side_permutation(Side::Left)
+    }
+
+    /// Input positions of the right rows in the order the first predicate
+    /// sorted them, see [`InequalityJoin::left_permutation`].
+    pub fn right_permutation(&self) -> Vec<usize> {
+        self.side_permutation(Side::Right)
+    }
+
+    fn side_permutation(&self, side: Side) -> Vec<usize> {
+        self.l1
+            .values
+            .iter()
+            .filter(|(_, s, _)| *s == side)
+            .map(|(_, _, idx)| *idx)
+            .collect()
+    }
+
+    /// Lower and upper bounds on the number of matches, without running the
+    /// scan.
+    ///
+    /// The upper bound is the smaller of the number of pairs satisfying each
+    /// predicate on its own, counted from the sorted unions in linear time.
+    /// Saturates at `usize::MAX`.
+    pub fn cardinality_bounds(&self) -> (usize, usize) {
+        let pairs1 = ordered_pairs(self.l1.values.iter().map(|(_, s, _)| s.is_left()));
+        let pairs2 = ordered_pairs(
+            self.l2
+                .values
+                .iter()
+                .map(|&(_, p)| self.l1.values[p].1.is_left()),
+        );
+        (0, pairs1.min(pairs2))
+    }
+
+    /// Map a match from `next_match` to input positions.
+    fn match_indices(&self, m: (usize, usize)) -> (usize, usize) {
+        union_indices(&self.l1, &self.l2, m)
+    }
+}
+
+/// Scan prebuilt unions, yielding matches as `(left_idx, right_idx)` input
+/// positions.
+///
+/// `l2` must be built with the permutation returned when building `l1`, see
+/// [`L1Union::from_predicate`] and [`L2Union::from_predicate`].
+pub fn scan<'a, T1, T2>(
+    l1: &'a L1Union<T1>,
+    l2: &'a L2Union<T2>,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut bitvec = L1BitVec::default();
+    bitvec.reset_with_size(l1.values.len());
+    let l2_end = scan_end(l1, l2);
+    let mut l2_idx = 0;
+
+    std::iter::from_fn(move || {
+        let m = scan_next(l1, l2, &mut l2_idx, l2_end, &mut bitvec)?;
+        Some(union_indices(l1, l2, m))
+    })
+}
+
+/// One past the last right entry in L2.
+fn scan_end<T1, T2>(l1: &L1Union<T1>, l2: &L2Union<T2>) -> usize {
+    l2.values
+        .iter()
+        .rposition(|(_, permuted)| !l1.values[*permuted].1.is_left())
+        .map_or(0, |idx| idx + 1)
+}
+
+/// Advance the scan from `l2_idx` to the next match, returning the L1
+/// position of the left entry and the L2 position of the right entry.
+fn scan_next<T1, T2>(
+    l1: &L1Union<T1>,
+    l2: &L2Union<T2>,
+    l2_idx: &mut usize,
+    l2_end: usize,
+    bitvec: &mut L1BitVec,
+) -> Option<(usize, usize)> {
+    while *l2_idx < l2_end {
+        let (_, permuted) = l2.values[*l2_idx];
+        if l1.values[permuted].1.is_left() {
+            *l2_idx += 1;
+            bitvec.set(permuted);
+            continue;
+        }
+
+        bitvec.maybe_reset_end(permuted);
+        if let Some(idx) = bitvec.next() {
+            return Some((idx, *l2_idx));
+        }
+
+        *l2_idx += 1;
+    }
+    None
+}
+
+/// Count the left and right entries where the left sorts first, given the
+/// side of each entry of a union in order. Every pair satisfying a predicate
+/// is counted by its union. Saturates at `usize::MAX`.
+fn ordered_pairs(is_left: impl Iterator<Item = bool>) -> usize {
+    let (mut lefts, mut pairs) = (0usize, 0usize);
+    for is_left in is_left {
+        if is_left {
+            lefts += 1;
+        } else {
+            pairs = pairs.saturating_add(lefts);
+        }
+    }
+    pairs
+}
+
+/// Map a match from `scan_next` to input positions.
+fn union_indices<T1, T2>(
+    l1: &L1Union<T1>,
+    l2: &L2Union<T2>,
+    (l1_idx, l2_idx): (usize, usize),
+) -> (usize, usize) {
+    let (_, _, left_idx) = l1.values[l1_idx];
+    let (_, permuted) = l2.values[l2_idx];
+    let (_, _, right_idx) = l1.values[permuted];
+    (left_idx, right_idx)
+}
+
+/// Sorted unions of a join that hasn't started, see
+/// [`InequalityJoin::prepare`].
+#[derive(Debug)]
+pub struct PreparedJoin<T1, T2> {
+    l1: L1Union<T1>,
+    l2: L2Union<T2>,
+    left_len: usize,
+    right_len: usize,
+}
+
+impl<T1, T2> PreparedJoin<T1, T2> {
+    /// Number of left rows.
+    pub fn left_len(&self) -> usize {
+        self.left_len
+    }
+
+    /// Number of right rows.
+    pub fn right_len(&self) -> usize {
+        self.right_len
+    }
+
+    /// Bytes [`InequalityJoin::start`] will allocate for the bitvec, one bit
+    /// per left and right row.
+    pub fn bitvec_bytes(&self) -> usize {
+        self.l1.values.len().div_ceil(8)
+    }
+
+    /// Smallest and largest key of the first predicate across both sides.
+    pub fn key1_range(&self) -> Option<(&T1, &T1)> {
+        let first = &self.l1.values.first()?.0;
+        let last = &self.l1.values.last()?.0;
+        Some(match self.l1.order.op.sort_order() {
+            SortOrder::Asc => (first, last),
+            SortOrder::Desc => (last, first),
+        })
+    }
+
+    /// Smallest and largest key of the second predicate across both sides.
+    pub fn key2_range(&self) -> Option<(&T2, &T2)> {
+        let first = &self.l2.values.first()?.0;
+        let last = &self.l2.values.last()?.0;
+        Some(match self.l2.order.op.sort_order() {
+            SortOrder::Asc => (first, last),
+            SortOrder::Desc => (last, first),
+        })
+    }
+}
+
+/// See [`JoinPredicate::fingerprint`].
+fn fingerprint(op: CmpOp, left_len: usize, right_len: usize) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let op: u8 = match op {
+        CmpOp::Lt => 0,
+        CmpOp::LtEq => 1,
+        CmpOp::Gt => 2,
+        CmpOp::GtEq => 3,
+    };
+    let left = (left_len as u64).to_le_bytes();
+    let right = (right_len as u64).to_le_bytes();
+    std::iter::once(op)
+        .chain(left)
+        .chain(right)
+        .fold(OFFSET, |hash, b| (hash ^ b as u64).wrapping_mul(PRIME))
+}
+
+/// Allocations from a finished join that can be reused by the next one.
+#[derive(Debug, Default)]
+pub struct JoinScratch {
+    bitvec: L1BitVec,
+}
+
+/// Progress of a scan, see [`InequalityJoin::snapshot`].
+#[derive(Debug, Clone)]
+pub struct ScanSnapshot {
+    /// Identifies the unions the snapshot was taken from.
+    unions_id: (u64, u64, usize),
+    l2_idx: usize,
+    bitvec: L1BitVec,
+}
+
+/// Part of a scan yielding matches as input positions, see
+/// [`InequalityJoin::split_at`] and [`shared::SharedJoin::inner`]. A clone
+/// continues from the same point.
+#[derive(Debug)]
+pub struct ScanRange<T1, T2> {
+    unions: Arc<(L1Union<T1>, L2Union<T2>)>,
+    l2_idx: usize,
+    l2_end: usize,
+    bitvec: L1BitVec,
+}
+
+impl<T1, T2> Iterator for ScanRange<T1, T2> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (l1, l2) = &*self.unions;
+        let m = scan_next(l1, l2, &mut self.l2_idx, self.l2_end, &mut self.bitvec)?;
+        Some(union_indices(l1, l2, m))
+    }
+}
+
+impl<T1, T2> Clone for ScanRange<T1, T2> {
+    fn clone(&self) -> Self {
+        ScanRange {
+            unions: self.unions.clone(),
+            l2_idx: self.l2_idx,
+            l2_end: self.l2_end,
+            bitvec: self.bitvec.clone(),
+        }
+    }
+}
+
+/// Unmatched left rows as input positions, see
+/// [`InequalityJoin::unmatched_lefts`].
+#[derive(Debug)]
+pub struct UnmatchedLefts<T1, T2> {
+    unions: Arc<(L1Union<T1>, L2Union<T2>)>,
+    reach: Vec<usize>,
+    l2_idx: usize,
+    /// L1 positions of scanned left entries that haven't matched yet, but
+    /// still could.
+    pending: BTreeSet<usize>,
+    /// L1 positions of left entries that can no longer match.
+    ready: Vec<usize>,
+}
+
+impl<T1, T2> UnmatchedLefts<T1, T2> {
+    fn new(unions: Arc<(L1Union<T1>, L2Union<T2>)>) -> Self {
+        let (l1, l2) = &*unions;
+        // reach[i] is the highest L1 position of a right entry in L2[i..].
+        let mut reach = vec![0; l2.values.len() + 1];
+        for (i, &(_, permuted)) in l2.values.iter().enumerate().rev() {
+            reach[i] = match l1.values[permuted].1 {
+                Side::Left => reach[i + 1],
+                Side::Right => reach[i + 1].max(permuted),
+            };
+        }
+
+        UnmatchedLefts {
+            unions,
+            reach,
+            l2_idx: 0,
+            pending: BTreeSet::new(),
+            ready: Vec::new(),
+        }
+    }
+}
+
+impl<T1, T2> Clone for UnmatchedLefts<T1, T2> {
+    fn clone(&self) -> Self {
+        UnmatchedLefts {
+            unions: self.unions.clone(),
+            reach: self.reach.clone(),
+            l2_idx: self.l2_idx,
+            pending: self.pending.clone(),
+            ready: self.ready.clone(),
+        }
+    }
+}
+
+impl<T1, T2> Iterator for UnmatchedLefts<T1, T2> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (l1, l2) = &*self.unions;
+        loop {
+            if let Some(pos) = self.ready.pop() {
+                let (_, _, left_idx) = l1.values[pos];
+                return Some(left_idx);
+            }
+
+            let &(_, permuted) = l2.values.get(self.l2_idx)?;
+            match l1.values[permuted].1 {
+                Side::Left => {
+                    self.pending.insert(permuted);
+                }
+                Side::Right => {
+                    // Every pending left before this entry in L1 matches it.
+                    self.pending = self.pending.split_off(&permuted);
+                }
+            }
+            self.l2_idx += 1;
+
+            let unreachable = self.pending.split_off(&self.reach[self.l2_idx]);
+            self.ready.extend(unreachable.into_iter().rev());
+        }
+    }
+}
+
+/// Left outer join as input positions, see [`InequalityJoin::left_outer`].
+#[derive(Debug)]
+pub struct LeftOuterJoin<T1, T2> {
+    join: InequalityJoin<T1, T2>,
+    /// Left rows with at least one match, by input position.
+    matched: BitVec,
+    /// Next left row to check once the scan is exhausted.
+    drain_idx: Option<usize>,
+}
+
+impl<T1, T2> Iterator for LeftOuterJoin<T1, T2> {
+    type Item = (usize, Option<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.drain_idx.is_none() {
+            if let Some(m) = self.join.next_match() {
+                let (left_idx, right_idx) = self.join.match_indices(m);
+                self.matched.set(left_idx, true);
+                return Some((left_idx, Some(right_idx)));
+            }
+            self.drain_idx = Some(0);
+        }
+
+        let drain_idx = self.drain_idx.as_mut()?;
+        let left_idx = self.matched[*drain_idx..].first_zero()? + *drain_idx;
+        *drain_idx = left_idx + 1;
+        Some((left_idx, None))
+    }
+}
+
+/// Join that can look at its next match, see
+/// [`InequalityJoin::into_peekable`].
+#[derive(Debug)]
+pub struct PeekableJoin<T1, T2> {
+    join: InequalityJoin<T1, T2>,
+    /// Match produced by `peek` and not yet consumed. `Some(None)` once the
+    /// scan is known to be exhausted.
+    peeked: Option<Option<(T1, T2)>>,
+}
+
+impl<T1, T2> PeekableJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    /// Return the next match without consuming it.
+    pub fn peek(&mut self) -> Option<&(T1, T2)> {
+        let join = &mut self.join;
+        self.peeked.get_or_insert_with(|| join.next()).as_ref()
+    }
+}
+
+impl<T1, T2> Iterator for PeekableJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    type Item = (T1, T2);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.join.next(),
+        }
+    }
+}
+
+/// Matches as input positions, see [`InequalityJoin::into_indices`].
+#[derive(Debug)]
+pub struct InequalityJoinIndices<T1, T2> {
+    join: InequalityJoin<T1, T2>,
+}
+
+impl<T1, T2> InequalityJoinIndices<T1, T2> {
+    /// Map each match to references into `left` and `right`, indexed by the
+    /// match's input positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics when a match is yielded whose position is out of bounds for its
+    /// payload slice.
+    pub fn map_payloads<'a, L, R>(
+        self,
+        left: &'a [L],
+        right: &'a [R],
+    ) -> impl Iterator<Item = (&'a L, &'a R)> {
+        self.map(move |(l, r)| (&left[l], &right[r]))
+    }
+}
+
+impl<T1, T2> Iterator for InequalityJoinIndices<T1, T2> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.join.next_match()?;
+        Some(self.join.match_indices(m))
+    }
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    /// Clear `out` and fill it with the join's matches, reusing its
+    /// allocation.
+    pub fn collect_into(self, out: &mut Vec<(T1, T2)>) {
+        out.clear();
+        out.extend(self);
+    }
+
+    /// Find the largest or smallest matching right key of the second
+    /// predicate for every left row, yielding `(left_idx, key)` in left input
+    /// order. Left rows without matches have no key.
+    ///
+    /// Only the running extremum for each left is kept while scanning.
+    pub fn extremum_per_left(
+        mut self,
+        kind: ExtremumKind,
+    ) -> impl Iterator<Item = (usize, Option<T2>)> {
+        let mut extremes: Vec<Option<T2>> = vec![None; self.left_len];
+        while let Some((l1_idx, l2_idx)) = self.next_match() {
+            let (left_idx, _) = self.match_indices((l1_idx, l2_idx));
+            let key = &self.l2.values[l2_idx].0;
+            let replace = match &extremes[left_idx] {
+                None => true,
+                Some(curr) => match kind {
+                    ExtremumKind::Max => key > curr,
+                    ExtremumKind::Min => key < curr,
+                },
+            };
+            if replace {
+                extremes[left_idx] = Some(key.clone());
+            }
+        }
+        extremes.into_iter().enumerate()
+    }
+
+    /// Wrap the join so the next match can be looked at without consuming it.
+    pub fn into_peekable(self) -> PeekableJoin<T1, T2> {
+        PeekableJoin {
+            join: self,
+            peeked: None,
+        }
+    }
+
+    /// Count the distinct matching right keys of the second predicate for
+    /// every left row, in left input order.
+    ///
+    /// Right rows with equal keys are counted once.
+    pub fn distinct_right_counts(mut self) -> Vec<usize>
+    where
+        T2: Hash,
+    {
+        let mut distinct: Vec<HashSet<T2>> = (0..self.left_len).map(|_| HashSet::new()).collect();
+        while let Some((l1_idx, l2_idx)) = self.next_match() {
+            let (left_idx, _) = self.match_indices((l1_idx, l2_idx));
+            distinct[left_idx].insert(self.l2.values[l2_idx].0.clone());
+        }
+        distinct.iter().map(HashSet::len).collect()
+    }
+
+    /// Collect matches with the keys of both predicates for both sides, as
+    /// `(left_key1, left_key2, right_key1, right_key2)`.
+    pub fn collect_full_keys(mut self) -> Vec<(T1, T2, T1, T2)> {
+        // L2 position of every L1 entry, to find the left's second key.
+        let mut l2_pos = vec![0; self.l2.values.len()];
+        for (pos, &(_, permuted)) in self.l2.values.iter().enumerate() {
+            l2_pos[permuted] = pos;
+        }
+
+        let mut out = Vec::new();
+        while let Some((l1_idx, l2_idx)) = self.next_match() {
+            let (right2, permuted) = &self.l2.values[l2_idx];
+            out.push((
+                self.l1.values[l1_idx].0.clone(),
+                self.l2.values[l2_pos[l1_idx]].0.clone(),
+                self.l1.values[*permuted].0.clone(),
+                right2.clone(),
+            ));
+        }
+        out
+    }
+
+    /// As-of join, pairing every left row with at most one matching right row,
+    /// yielding `(left_idx, right_idx)` in left input order.
+    ///
+    /// The right kept is the one with the largest or smallest key in the
+    /// first predicate, per `direction`. Rights with equal keys are broken by
+    /// the lowest input position. Left rows without matches have no right.
+    pub fn asof(
+        mut self,
+        direction: AsofDirection,
+    ) -> impl Iterator<Item = (usize, Option<usize>)> {
+        // L1 position of the right's first key, and its input position.
+        let mut best: Vec<Option<(usize, usize)>> = vec![None; self.left_len];
+        while let Some((l1_idx, l2_idx)) = self.next_match() {
+            let (left_idx, right_idx) = self.match_indices((l1_idx, l2_idx));
+            let permuted = self.l2.values[l2_idx].1;
+            let replace = match best[left_idx] {
+                None => true,
+                Some((curr, curr_idx)) => {
+                    let ord = self.l1.values[permuted].0.cmp(&self.l1.values[curr].0);
+                    let ord = match direction {
+                        AsofDirection::Backward => ord,
+                        AsofDirection::Forward => ord.reverse(),
+                    };
+                    ord.then_with(|| curr_idx.cmp(&right_idx)) == Ordering::Greater
+                }
+            };
+            if replace {
+                best[left_idx] = Some((permuted, right_idx));
+            }
+        }
+        best.into_iter()
+            .enumerate()
+            .map(|(left_idx, best)| (left_idx, best.map(|(_, right_idx)| right_idx)))
+    }
+
+    /// Count the matches for every left row, yielding `(left_key, count)` in
+    /// the order lefts are visited by the scan.
+    ///
+    /// A left's count is only final once every right entry after it in L2 has
+    /// probed, so the scan runs to completion first. Only a count per left is
+    /// kept, the matches themselves aren't buffered.
+    pub fn left_counts(mut self) -> impl Iterator<Item = (T1, usize)> {
+        let mut counts = vec![0; self.l1.values.len()];
+        while let Some((l1_idx, _)) = self.next_match() {
+            counts[l1_idx] += 1;
+        }
+
+        let l1 = self.l1;
+        self.l2
+            .values
+            .into_iter()
+            .filter_map(move |(_, permuted)| match &l1.values[permuted] {
+                (v, Side::Left, _) => Some((v.clone(), counts[permuted])),
+                _ => None,
+            })
+    }
+
+    /// Iterate over matches in batches of `size`. Only the final batch may be
+    /// smaller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn chunks(mut self, size: usize) -> impl Iterator<Item = Vec<(T1, T2)>> {
+        assert!(size > 0, "chunk size must be non-zero");
+        std::iter::from_fn(move || {
+            let mut chunk = Vec::with_capacity(size);
+            chunk.extend(self.by_ref().take(size));
+            if chunk.is_empty() {
+                None
+            } else {
+                Some(chunk)
+            }
+        })
+    }
+
+    /// Iterate over matches ordered by non-decreasing right key.
+    ///
+    /// The scan visits right keys in L2 order, which is already ascending for
+    /// `Lt` and `LtEq`. For `Gt` and `GtEq` the output is buffered and
+    /// reversed.
+    pub fn sorted_by_right(self) -> impl Iterator<Item = (T1, T2)> {
+        match self.l2.order.op.sort_order() {
+            SortOrder::Asc => SortedByRight::Scan(self),
+            SortOrder::Desc => {
+                let out: Vec<_> = self.collect();
+                SortedByRight::Buffered(out.into_iter().rev())
+            }
+        }
+    }
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Clone + Debug,
+{
+    /// Group the matching right keys by left row.
+    ///
+    /// Matches are gathered into a single buffer with each left's right keys
+    /// stored contiguously, so every group can be borrowed as a slice.
+    pub fn grouped_refs(mut self) -> GroupedRefs<T1, T2> {
+        let mut matches = Vec::new();
+        while let Some(m) = self.next_match() {
+            matches.push(m);
+        }
+
+        // Stable sort keeps the scan order of rights within each left.
+        matches.sort_by_key(|(l1_idx, _)| self.l1.values[*l1_idx].2);
+
+        let mut lefts = Vec::new();
+        let mut offsets = Vec::new();
+        let mut rights = Vec::with_capacity(matches.len());
+        for (l1_idx, l2_idx) in matches {
+            if lefts.last() != Some(&l1_idx) {
+                lefts.push(l1_idx);
+                offsets.push(rights.len());
+            }
+            rights.push(self.l2.values[l2_idx].0.clone());
+        }
+        offsets.push(rights.len());
+
+        GroupedRefs {
+            l1: self.l1,
+            lefts,
+            offsets,
+            rights,
+        }
+    }
+
+    /// Group the matching right keys by left key, in ascending left key
+    /// order.
+    ///
+    /// Left rows with equal keys share a group. Groups are found by sorting
+    /// the matches, so keys only need `Ord`, not `Hash`.
+    pub fn group_by_left_sorted(self) -> Vec<(T1, Vec<T2>)>
+    where
+        T1: Clone,
+    {
+        let mut matches: Vec<_> = self.collect();
+        // Stable sort keeps the scan order of rights within each group.
+        matches.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut groups: Vec<(T1, Vec<T2>)> = Vec::new();
+        for (left, right) in matches {
+            match groups.last_mut() {
+                Some((key, rights)) if *key == left => rights.push(right),
+                _ => groups.push((left, vec![right])),
+            }
+        }
+        groups
+    }
+}
+
+/// Matching right keys grouped by left row, see
+/// [`InequalityJoin::grouped_refs`].
+#[derive(Debug)]
+pub struct GroupedRefs<T1, T2> {
+    l1: L1Union<T1>,
+    /// L1 positions of lefts with at least one match, in left input order.
+    lefts: Vec<usize>,
+    offsets: Vec<usize>,
+    rights: Vec<T2>,
+}
+
+impl<T1, T2> GroupedRefs<T1, T2> {
+    /// Iterate over each matched left key along with its matching right keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&T1, &[T2])> {
+        self.lefts.iter().enumerate().map(move |(i, pos)| {
+            let (v1, _, _) = &self.l1.values[*pos];
+            (v1, &self.rights[self.offsets[i]..self.offsets[i + 1]])
+        })
+    }
+}
+
+enum SortedByRight<T1, T2> {
+    Scan(InequalityJoin<T1, T2>),
+    Buffered(std::iter::Rev<std::vec::IntoIter<(T1, T2)>>),
+}
+
+impl<T1, T2> Iterator for SortedByRight<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    type Item = (T1, T2);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedByRight::Scan(join) => join.next(),
+            SortedByRight::Buffered(iter) => iter.next(),
+        }
+    }
+}
+
+/// Extension for joining iterators of `(key1, key2)` rows.
+pub trait JoinExt<T1, T2>: Iterator<Item = (T1, T2)> + Sized
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Join these left rows with `right` where `left.0 op1 right.0` and
+    /// `left.1 op2 right.1`.
+    fn inequality_join<R>(self, op1: CmpOp, op2: CmpOp, right: R) -> InequalityJoin<T1, T2>
+    where
+        R: IntoIterator<Item = (T1, T2)>,
+    {
+        let (left1, left2): (Vec<_>, Vec<_>) = self.unzip();
+        let (right1, right2): (Vec<_>, Vec<_>) = right.into_iter().unzip();
+        InequalityJoin::new(
+            JoinPredicate::new(op1, left1, right1),
+            JoinPredicate::new(op2, left2, right2),
+        )
+    }
+}
+
+impl<I, T1, T2> JoinExt<T1, T2> for I
+where
+    I: Iterator<Item = (T1, T2)>,
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+}
+
+/// Bits for left entries already visited in the L2 scan, indexed by L1
+/// position.
+///
+/// Iterating yields the set positions before the current end, i.e. the left
+/// entries that also precede the probing right entry in L1.
+#[derive(Debug, Clone, Default)]
+struct L1BitVec {
+    bitvec: BitVec,
+    idx: usize,
+    end_idx: usize,
+}
+
+impl L1BitVec {
+    /// Clear all bits and resize to `size`, reusing the existing allocation
+    /// where possible.
+    fn reset_with_size(&mut self, size: usize) {
+        self.bitvec.clear();
+        self.bitvec.resize(size, false);
+        self.idx = 0;
+        self.end_idx = 0;
+    }
+
+    fn maybe_reset_end(&mut self, end_idx: usize) {
+        if end_idx == self.end_idx {
+            return;
+        }
+        self.idx = 0;
+        self.end_idx = end_idx;
+    }
+
+    fn set(&mut self, idx: usize) {
+        self.bitvec.set(idx, true);
+    }
+
+    /// Count the set bits in `[0, end)` a word at a time.
+    fn count_set_in_range(&self, end: usize) -> usize {
+        let words = self.bitvec.as_raw_slice();
+        let (full, rem) = (end / usize::BITS as usize, end % usize::BITS as usize);
+
+        let mut count: usize = words[..full].iter().map(|w| w.count_ones() as usize).sum();
+        if rem > 0 {
+            // Bits are stored least significant first.
+            count += (words[full] & ((1 << rem) - 1)).count_ones() as usize;
+        }
+        count
+    }
+}
+
+impl Iterator for L1BitVec {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx >= self.end_idx {
+            return None;
+        }
+        // Skips a word of unset bits at a time.
+        match self.bitvec[self.idx..self.end_idx].first_one() {
+            Some(offset) => {
+                let idx = self.idx + offset;
+                self.idx = idx + 1;
+                Some(idx)
+            }
+            None => {
+                self.idx = self.end_idx;
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Input a key comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn is_left(&self) -> bool {
+        matches!(self, Side::Left)
+    }
+}
+
+/// Left and right keys of the first predicate, sorted.
+#[derive(Debug)]
+pub struct L1Union<T> {
+    values: Vec<(T, Side, usize)>,
+    order: KeyOrder,
+}
+
+impl<T> L1Union<T>
+where
+    T: Ord,
+{
+    /// Build the union for the first predicate, along with the permutation
+    /// used to build the second union with [`L2Union::from_predicate`].
+    pub fn from_predicate(join: JoinPredicate<T>) -> (Vec<usize>, Self) {
+        let order = join.key_order();
+        Self::union_and_sort(join.left, join.right, &order)
+    }
+
+    /// Union both sides and sort by the first predicate.
+    ///
+    /// Also returns the position in the sorted union for every input value,
+    /// indexed by the value's position in `left` chained with `right`.
+    fn union_and_sort(left: Vec<T>, right: Vec<T>, order: &KeyOrder) -> (Vec<usize>, Self) {
+        let left_len = left.len();
+        let mut values = Self::union(left, right);
+        match order.presorted {
+            Some(input) => {
+                let right = values.split_off(left_len);
+                let reverse = input != order.op.sort_order();
+                values = merge_presorted(values, right, reverse, |(v1, s1, _), (v2, s2, _)| {
+                    order.compare((v1, s1), (v2, s2))
+                });
+            }
+            None => values.sort_unstable_by(|a, b| Self::compare(order, a, b)),
+        }
+        Self::from_sorted(values, order)
+    }
+
+    fn compare(
+        order: &KeyOrder,
+        (v1, s1, i1): &(T, Side, usize),
+        (v2, s2, i2): &(T, Side, usize),
+    ) -> Ordering {
+        order.compare((v1, s1), (v2, s2)).then_with(|| i1.cmp(i2))
+    }
+
+    fn union(left: Vec<T>, right: Vec<T>) -> Vec<(T, Side, usize)> {
+        let left = left
+            .into_iter()
+            .enumerate()
+            .map(|(idx, v)| (v, Side::Left, idx));
+        let right = right
+            .into_iter()
+            .enumerate()
+            .map(|(idx, v)| (v, Side::Right, idx));
+
+        left.chain(right).collect()
+    }
+
+    fn from_sorted(values: Vec<(T, Side, usize)>, order: &KeyOrder) -> (Vec<usize>, Self) {
+        let left_len = values.iter().filter(|(_, side, _)| side.is_left()).count();
+
+        let mut perms = vec![0; values.len()];
+        for (pos, (_, side, idx)) in values.iter().enumerate() {
+            match side {
+                Side::Left => perms[*idx] = pos,
+                Side::Right => perms[left_len + idx] = pos,
+            }
+        }
+        debug_assert_permutation(&perms);
+
+        (
+            perms,
+            L1Union {
+                values,
+                order: *order,
+            },
+        )
+    }
+}
+
+/// Merge two sides that are each already sorted, reversing them first if
+/// `reverse` is set.
+///
+/// Only compares values across sides, taking O(n) instead of the O(n log n)
+/// of sorting the union. Equal values within a side keep their (possibly
+/// reversed) input order.
+fn merge_presorted<E, F>(mut left: Vec<E>, mut right: Vec<E>, reverse: bool, mut cmp: F) -> Vec<E>
+where
+    F: FnMut(&E, &E) -> Ordering,
+{
+    if reverse {
+        left.reverse();
+        right.reverse();
+    }
+    debug_assert!(left
+        .windows(2)
+        .all(|w| cmp(&w[0], &w[1]) != Ordering::Greater));
+    debug_assert!(right
+        .windows(2)
+        .all(|w| cmp(&w[0], &w[1]) != Ordering::Greater));
+
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    loop {
+        let take_left = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => cmp(l, r) != Ordering::Greater,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_left {
+            out.extend(left.next());
+        } else {
+            out.extend(right.next());
+        }
+    }
+    out
+}
+
+/// Left and right keys of the second predicate, sorted, with the position of
+/// each row in the first union.
+#[derive(Debug)]
+pub struct L2Union<T> {
+    values: Vec<(T, usize)>,
+    order: KeyOrder,
+}
+
+impl<T> L2Union<T>
+where
+    T: Ord,
+{
+    /// Build the union for the second predicate, with the permutation from
+    /// [`L1Union::from_predicate`].
+    pub fn from_predicate(join: JoinPredicate<T>, permutations: Vec<usize>) -> Self {
+        let order = join.key_order();
+        let left_len = join.left.len();
+        Self::union_and_sort_with_permutations(
+            join.left,
+            join.right,
+            &order,
+            left_len,
+            permutations,
+        )
+    }
+
+    /// Union both sides and sort by the second predicate, replacing each
+    /// value's input position with its position in L1.
+    fn union_and_sort_with_permutations(
+        left: Vec<T>,
+        right: Vec<T>,
+        order: &KeyOrder,
+        left_len: usize,
+        permutations: Vec<usize>,
+    ) -> Self {
+        let mut values = Self::union(left, right);
+        match order.presorted {
+            Some(input) => {
+                let right = values.split_off(left_len);
+                let reverse = input != order.op.sort_order();
+                values = merge_presorted(values, right, reverse, |(v1, i1), (v2, i2)| {
+                    order.compare(
+                        (v1, &Self::side(*i1, left_len)),
+                        (v2, &Self::side(*i2, left_len)),
+                    )
+                });
+            }
+            None => values.sort_unstable_by(|a, b| Self::compare(order, left_len, a, b)),
+        }
+        Self::from_sorted(values, order, permutations)
+    }
+
+    fn compare(
+        order: &KeyOrder,
+        left_len: usize,
+        (v1, i1): &(T, usize),
+        (v2, i2): &(T, usize),
+    ) -> Ordering {
+        order
+            .compare(
+                (v1, &Self::side(*i1, left_len)),
+                (v2, &Self::side(*i2, left_len)),
+            )
+            .then_with(|| i1.cmp(i2))
+    }
+
+    fn union(left: Vec<T>, right: Vec<T>) -> Vec<(T, usize)> {
+        left.into_iter()
+            .chain(right)
+            .enumerate()
+            .map(|(idx, v)| (v, idx))
+            .collect()
+    }
+
+    fn side(idx: usize, left_len: usize) -> Side {
+        if idx < left_len {
+            Side::Left
+        } else {
+            Side::Right
+        }
+    }
+
+    fn from_sorted(
+        mut values: Vec<(T, usize)>,
+        order: &KeyOrder,
+        permutations: Vec<usize>,
+    ) -> Self {
+        debug_assert_eq!(values.len(), permutations.len());
+        debug_assert_permutation(&permutations);
+
+        for (_, idx) in values.iter_mut() {
+            *idx = permutations[*idx];
+        }
+        L2Union {
+            values,
+            order: *order,
+        }
+    }
+
+    /// Sort the union of `left` and `right` from scratch, without the
+    /// permutation from L1, returning the side and input position of each
+    /// entry in sorted order.
+    #[cfg(test)]
+    fn union_and_sort_independent(left: &[T], right: &[T], order: &KeyOrder) -> Vec<(Side, usize)> {
+        let key = |(side, idx): &(Side, usize)| match side {
+            Side::Left => &left[*idx],
+            Side::Right => &right[*idx],
+        };
+        let mut entries: Vec<_> = (0..left.len())
+            .map(|idx| (Side::Left, idx))
+            .chain((0..right.len()).map(|idx| (Side::Right, idx)))
+            .collect();
+        entries.sort_by(|a, b| order.compare((key(a), &a.0), (key(b), &b.0)));
+        entries
+    }
+
+    /// Assert that the union agrees with [`L2Union::union_and_sort_independent`].
+    ///
+    /// Each position must hold an entry from the same side, and the entry
+    /// reached through L1 must have the key stored in L2. Entries with equal
+    /// keys on the same side may be in any order.
+    #[cfg(test)]
+    fn assert_agrees_independent<T1>(&self, l1: &L1Union<T1>, left: &[T], right: &[T]) {
+        let key = |(side, idx): &(Side, usize)| match side {
+            Side::Left => &left[*idx],
+            Side::Right => &right[*idx],
+        };
+        let independent = Self::union_and_sort_independent(left, right, &self.order);
+        assert_eq!(independent.len(), self.values.len());
+
+        for (pos, ((v, permuted), expected)) in self.values.iter().zip(&independent).enumerate() {
+            let (_, side, idx) = &l1.values[*permuted];
+            let actual = (side.clone(), *idx);
+            assert_eq!(expected.0, actual.0, "side at {}", pos);
+            assert!(key(expected) == v, "independent key at {}", pos);
+            assert!(key(&actual) == v, "permuted key at {}", pos);
+        }
+    }
+}
+
+/// Assert that `perms` contains every index in `0..perms.len()` exactly once.
+fn debug_assert_permutation(perms: &[usize]) {
+    if cfg!(debug_assertions) {
+        let mut seen = BitVec::<usize>::repeat(false, perms.len());
+        for &p in perms {
+            assert!(p < perms.len(), "permutation index out of range: {}", p);
+            assert!(!seen[p], "permutation index repeated: {}", p);
+            seen.set(p, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Predicates over five lefts and four rights, with ties within and
+    /// across sides.
+    pub(crate) fn fixture_with(op1: CmpOp, op2: CmpOp) -> (JoinPredicate<i32>, JoinPredicate<i32>) {
+        (
+            JoinPredicate::new(op1, vec![4, 1, 7, 3, 3], vec![5, 2, 8, 3]),
+            JoinPredicate::new(op2, vec![2, 9, 4, 4, 0], vec![1, 4, 6, 0]),
+        )
+    }
+
+    /// `left1 < right1 AND left2 >= right2` over [`fixture_with`]'s keys.
+    pub(crate) fn fixture() -> (JoinPredicate<i32>, JoinPredicate<i32>) {
+        fixture_with(CmpOp::Lt, CmpOp::GtEq)
+    }
+
+    pub(crate) fn fixture_join() -> InequalityJoin<i32, i32> {
+        let (join1, join2) = fixture();
+        InequalityJoin::new(join1, join2)
+    }
+
+    /// Self join on `key1 > key1 AND key2 < key2` over four rows, matching
+    /// `(100, 10)` and `(90, 10)`.
+    pub(crate) fn simple_fixture() -> (JoinPredicate<i32>, JoinPredicate<i32>) {
+        (
+            JoinPredicate::new(CmpOp::Gt, vec![100, 140, 80, 90], vec![100, 140, 80, 90]),
+            JoinPredicate::new(CmpOp::Lt, vec![6, 11, 10, 5], vec![6, 11, 10, 5]),
+        )
+    }
+
+    pub(crate) fn simple_join() -> InequalityJoin<i32, i32> {
+        let (join1, join2) = simple_fixture();
+        InequalityJoin::new(join1, join2)
+    }
+
+    #[test]
+    fn simple() {
+        let (join1, join2) = simple_fixture();
+        let expected = vec![(100, 10), (90, 10)];
+
+        let iejoin = InequalityJoin::new(join1, join2);
+        let out: Vec<_> = iejoin.into_iter().collect();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn into_peekable() {
+        let expected: Vec<_> = fixture_join().collect();
+
+        let mut peekable = fixture_join().into_peekable();
+        let mut out = Vec::new();
+        while let Some(&peeked) = peekable.peek() {
+            // Peeking again doesn't advance.
+            assert_eq!(Some(&peeked), peekable.peek());
+            assert_eq!(Some(peeked), peekable.next());
+            out.push(peeked);
+        }
+        assert_eq!(None, peekable.next());
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn distinct_right_counts() {
+        // Rights 0 and 2 have the same key, as do rights 1 and 3.
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![0, 2, 9], vec![1, 3, 4, 5]),
+                JoinPredicate::new(CmpOp::Gt, vec![9, 9, 9], vec![1, 7, 1, 7]),
+            )
+        };
+        let mut raw = vec![0; 3];
+        for (left_idx, _) in join().into_indices() {
+            raw[left_idx] += 1;
+        }
+        assert_eq!(vec![4, 3, 0], raw);
+        assert_eq!(vec![2, 2, 0], join().distinct_right_counts());
+    }
+
+    #[test]
+    fn collect_full_keys() {
+        let (join1, join2) = simple_fixture();
+
+        let out = InequalityJoin::new(join1, join2).collect_full_keys();
+        assert_eq!(vec![(100, 6, 80, 10), (90, 5, 80, 10)], out);
+    }
+
+    #[test]
+    fn matched_mask() {
+        let (join1, join2) = simple_fixture();
+
+        let mask = InequalityJoin::new(join1, join2).matched_mask();
+        assert_eq!(4, mask.len());
+        assert_eq!(vec![0, 3], mask.iter_ones().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn partition() {
+        let (matches, unmatched) = fixture_join().partition();
+        let expected: Vec<_> = fixture_join().into_indices().collect();
+        assert_eq!(expected, matches);
+        assert_eq!(vec![2, 4], unmatched);
+
+        // Every left row is either matched or unmatched, but not both.
+        for left_idx in 0..5 {
+            let matched = matches.iter().any(|&(l, _)| l == left_idx);
+            assert_ne!(matched, unmatched.contains(&left_idx), "{}", left_idx);
+        }
+    }
+
+    #[test]
+    fn adjacency() {
+        let (join1, join2) = simple_fixture();
+
+        let rows = InequalityJoin::new(join1, join2).adjacency();
+        let ones: Vec<Vec<_>> = rows.iter().map(|row| row.iter_ones().collect()).collect();
+        assert_eq!(vec![vec![2], vec![], vec![], vec![2]], ones);
+        assert!(rows.iter().all(|row| row.len() == 4));
+    }
+
+    #[test]
+    fn to_csr() {
+        let (join1, join2) = simple_fixture();
+        let (offsets, rights) = InequalityJoin::new(join1, join2).to_csr();
+        assert_eq!(vec![0, 1, 1, 1, 2], offsets);
+        assert_eq!(vec![2, 2], rights);
+
+        let (offsets, rights) = fixture_join().to_csr();
+        let from_csr: Vec<_> = offsets
+            .windows(2)
+            .enumerate()
+            .flat_map(|(l, row)| rights[row[0]..row[1]].iter().map(move |&r| (l, r)))
+            .collect();
+        assert_eq!(fixture_join().collect_sorted(), from_csr);
+    }
+
+    #[test]
+    fn lens() {
+        let join1 = JoinPredicate::new(CmpOp::Gt, vec![100, 140, 80, 90], vec![100, 140, 80]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![6, 11, 10, 5], vec![6, 11, 10]);
+
+        let iejoin = InequalityJoin::new(join1, join2);
+        assert_eq!(4, iejoin.left_len());
+        assert_eq!(3, iejoin.right_len());
+        assert_eq!(12, iejoin.cartesian_size());
+    }
+
+    #[test]
+    fn permutations() {
+        let left1 = vec![4, 1, 7, 3, 3];
+        let right1 = vec![5, 2, 8, 3];
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Gt, left1.clone(), right1.clone()),
+            JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0], vec![1, 4, 6, 0]),
+        );
+
+        let left_perm = join.left_permutation();
+        let right_perm = join.right_permutation();
+        let sorted_left: Vec<_> = left_perm.iter().map(|&i| left1[i]).collect();
+        assert_eq!(vec![7, 4, 3, 3, 1], sorted_left);
+        let sorted_right: Vec<_> = right_perm.iter().map(|&i| right1[i]).collect();
+        assert_eq!(vec![8, 5, 3, 2], sorted_right);
+
+        // Inverting recovers the input order.
+        let mut inverse = vec![0; left_perm.len()];
+        for (pos, &idx) in left_perm.iter().enumerate() {
+            inverse[idx] = pos;
+        }
+        let recovered: Vec<_> = inverse.iter().map(|&pos| sorted_left[pos]).collect();
+        assert_eq!(left1, recovered);
+    }
+
+    #[test]
+    fn cardinality_bounds() {
+        let (join1, join2) = simple_fixture();
+
+        let iejoin = InequalityJoin::new(join1, join2);
+        assert_eq!((0, 6), iejoin.cardinality_bounds());
+        assert_eq!(16, iejoin.cartesian_size());
+        assert_eq!(2, iejoin.count());
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let (join1, join2) = fixture_with(op1, op2);
+                let join = InequalityJoin::new(join1, join2);
+                let (lower, upper) = join.cardinality_bounds();
+                let count = join.count();
+                assert!(lower <= count && count <= upper, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    const OPS: [CmpOp; 4] = [CmpOp::Lt, CmpOp::LtEq, CmpOp::Gt, CmpOp::GtEq];
+
+    /// Matches of both predicates as `(left_idx, right_idx)`, in ascending
+    /// order, found by checking every pair.
+    pub(crate) fn brute_force<T1: Ord, T2: Ord>(
+        join1: &JoinPredicate<T1>,
+        join2: &JoinPredicate<T2>,
+    ) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        for l in 0..join1.left.len() {
+            for r in 0..join1.right.len() {
+                if join1.op.holds(&join1.left[l], &join1.right[r])
+                    && join2.op.holds(&join2.left[l], &join2.right[r])
+                {
+                    matches.push((l, r));
+                }
+            }
+        }
+        matches
+    }
+
+    #[test]
+    fn from_slices() {
+        let left1 = [100, 140, 80, 90];
+        let left2 = [6, 11, 10, 5];
+        let right1 = left1;
+        let right2 = left2;
+
+        let join = InequalityJoin::new(
+            JoinPredicate::from_slices(CmpOp::Gt, &left1, &right1),
+            JoinPredicate::from_slices(CmpOp::Lt, &left2, &right2),
+        );
+        let out: Vec<_> = join.collect();
+        assert_eq!(vec![(&100, &10), (&90, &10)], out);
+
+        assert_eq!([100, 140, 80, 90], left1);
+        assert_eq!([6, 11, 10, 5], left2);
+    }
+
+    #[test]
+    fn reverse_keys() {
+        use std::cmp::Reverse;
+
+        let (join1, join2) = fixture();
+        let (left1, right1, left2, right2) = (join1.left, join1.right, join2.left, join2.right);
+        let rev = |v: &[i32]| v.iter().copied().map(Reverse).collect::<Vec<_>>();
+
+        for op1 in OPS {
+            for op2 in OPS {
+                // `Reverse(a) op Reverse(b)` is `b op a`, i.e. `a flip(op) b`.
+                let flip = |op| match op {
+                    CmpOp::Lt => CmpOp::Gt,
+                    CmpOp::LtEq => CmpOp::GtEq,
+                    CmpOp::Gt => CmpOp::Lt,
+                    CmpOp::GtEq => CmpOp::LtEq,
+                };
+                let expected = InequalityJoin::new(
+                    JoinPredicate::new(flip(op1), left1.clone(), right1.clone()),
+                    JoinPredicate::new(flip(op2), left2.clone(), right2.clone()),
+                )
+                .collect_sorted();
+
+                let out = InequalityJoin::new(
+                    JoinPredicate::new(op1, rev(&left1), rev(&right1)),
+                    JoinPredicate::new(op2, rev(&left2), rev(&right2)),
+                )
+                .collect_sorted();
+                assert_eq!(expected, out, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    #[test]
+    fn wide_keys() {
+        let big = i128::MAX - 10;
+        let left1 = vec![big, i128::MIN, -big, big + 5];
+        let left2 = vec![u128::MAX, 0, u128::MAX - 1, 1 << 100];
+        let right1 = vec![big + 1, i128::MIN + 1, big + 5];
+        let right2 = vec![u128::MAX - 1, 1 << 90, 1 << 100];
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, left1, right1);
+        let join2 = JoinPredicate::new(CmpOp::GtEq, left2, right2);
+        let expected = brute_force(&join1, &join2);
+        let out = InequalityJoin::new(join1, join2).collect_sorted();
+        assert_eq!(vec![(0, 0), (0, 2), (2, 0), (2, 2)], expected);
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn byte_keys() {
+        let left1: Vec<Vec<u8>> = vec![b"apple".to_vec(), b"cherry".to_vec()];
+        let right1: Vec<Vec<u8>> = vec![b"banana".to_vec(), b"app".to_vec()];
+        let left2: Vec<Vec<u8>> = vec![b"x".to_vec(), b"y".to_vec()];
+        let right2: Vec<Vec<u8>> = vec![b"a".to_vec(), b"b".to_vec()];
+
+        let owned: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+            JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone()),
+        )
+        .collect();
+        assert_eq!(vec![(b"apple".to_vec(), b"a".to_vec())], owned);
+
+        // Borrowed slices avoid cloning the bytes.
+        fn slices(v: &[Vec<u8>]) -> Vec<&[u8]> {
+            v.iter().map(Vec::as_slice).collect()
+        }
+        let borrowed: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, slices(&left1), slices(&right1)),
+            JoinPredicate::new(CmpOp::Gt, slices(&left2), slices(&right2)),
+        )
+        .collect();
+        assert_eq!(vec![(&b"apple"[..], &b"a"[..])], borrowed);
+    }
+
+    #[test]
+    fn time_keys() {
+        use std::time::{Duration, Instant};
+
+        let secs = |v: &[u64]| {
+            v.iter()
+                .map(|&s| Duration::from_secs(s))
+                .collect::<Vec<_>>()
+        };
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::LtEq, secs(&[10, 30, 50]), secs(&[20, 30])),
+            JoinPredicate::new(CmpOp::Gt, secs(&[5, 8, 1]), secs(&[4, 6])),
+        );
+        let mut out: Vec<_> = join.into_indices().collect();
+        out.sort_unstable();
+        assert_eq!(vec![(0, 0), (1, 1)], out);
+
+        // Instants from the same clock are Ord too.
+        let base = Instant::now();
+        let at = |v: &[u64]| {
+            v.iter()
+                .map(|&s| base + Duration::from_secs(s))
+                .collect::<Vec<_>>()
+        };
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::LtEq, at(&[10, 30, 50]), at(&[20, 30])),
+            JoinPredicate::new(CmpOp::Gt, at(&[5, 8, 1]), at(&[4, 6])),
+        );
+        let mut out: Vec<_> = join.into_indices().collect();
+        out.sort_unstable();
+        assert_eq!(vec![(0, 0), (1, 1)], out);
+    }
+
+    #[test]
+    fn cow_keys() {
+        use std::borrow::Cow;
+
+        let owned = String::from("m");
+        let left1: Vec<Cow<str>> = vec![Cow::Borrowed("b"), Cow::Owned("k".to_string())];
+        let right1: Vec<Cow<str>> = vec![Cow::Borrowed(&owned), Cow::Borrowed("c")];
+        let left2: Vec<Cow<str>> = vec![Cow::Borrowed("x"), Cow::Borrowed("y")];
+        let right2: Vec<Cow<str>> = vec![Cow::Borrowed("a"), Cow::Owned("z".to_string())];
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1, right1),
+            JoinPredicate::new(CmpOp::Gt, left2, right2),
+        );
+        let out: Vec<_> = join.collect();
+        assert_eq!(
+            vec![
+                (Cow::Borrowed("b"), Cow::Borrowed("a")),
+                (Cow::Borrowed("k"), Cow::Borrowed("a"))
+            ],
+            out
+        );
+    }
+
+    #[test]
+    fn arc_keys() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Big {
+            id: u32,
+            payload: Vec<u8>,
+        }
+        let big = |id| {
+            Arc::new(Big {
+                id,
+                payload: vec![0; 1024],
+            })
+        };
+
+        let left1 = vec![big(1), big(5)];
+        let right1 = vec![big(3), big(7)];
+        let left2 = vec![big(2), big(6)];
+        let right2 = vec![big(4), big(0)];
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1.clone(), right1),
+            JoinPredicate::new(CmpOp::Gt, left2, right2.clone()),
+        );
+        let out: Vec<_> = join.collect();
+        let ids: Vec<_> = out.iter().map(|(a, b)| (a.id, b.id)).collect();
+        assert_eq!(vec![(1, 0), (5, 0)], ids);
+
+        // Output keys share the inputs' allocations.
+        assert!(Arc::ptr_eq(&left1[0], &out[0].0));
+        assert!(Arc::ptr_eq(&right2[1], &out[0].1));
+    }
+
+    #[test]
+    fn single_element() {
+        for op1 in OPS {
+            for op2 in OPS {
+                for (l1, r1, l2, r2) in [(1, 2, 1, 2), (2, 1, 2, 1), (1, 1, 1, 1), (1, 2, 2, 1)] {
+                    let join1 = JoinPredicate::new(op1, vec![l1], vec![r1]);
+                    let join2 = JoinPredicate::new(op2, vec![l2], vec![r2]);
+                    let out: Vec<_> = InequalityJoin::new(join1, join2).collect();
+
+                    let expected = if op1.holds(&l1, &r1) && op2.holds(&l2, &r2) {
+                        vec![(l1, r2)]
+                    } else {
+                        Vec::new()
+                    };
+                    assert_eq!(expected, out, "{:?} {:?} {:?}", op1, op2, (l1, r1, l2, r2));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn non_monotonic_scan() {
+        // The second keys zig-zag against the first, so consecutive right
+        // entries in L2 probe L1 prefixes that shrink and grow.
+        let left1 = vec![1, 5, 9, 3, 7, 5];
+        let left2 = vec![6, 2, 8, 0, 4, 6];
+        let right1 = vec![8, 2, 6, 4, 10, 5];
+        let right2 = vec![1, 7, 3, 9, 5, 5];
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let join1 = || JoinPredicate::new(op1, left1.clone(), right1.clone());
+                let join2 = || JoinPredicate::new(op2, left2.clone(), right2.clone());
+
+                let (perms, l1) = L1Union::from_predicate(join1());
+                let l2 = L2Union::from_predicate(join2(), perms);
+                let ends: Vec<_> = l2
+                    .values
+                    .iter()
+                    .map(|&(_, p)| p)
+                    .filter(|&p| !l1.values[p].1.is_left())
+                    .collect();
+                assert!(ends.windows(2).any(|w| w[0] > w[1]), "{:?}", ends);
+                assert!(ends.windows(2).any(|w| w[0] < w[1]), "{:?}", ends);
+
+                let expected = brute_force(&join1(), &join2());
+                let out = InequalityJoin::new(join1(), join2()).collect_sorted();
+                assert_eq!(expected, out, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    #[test]
+    fn dense_scan() {
+        // Every right entry probes many set bits, so most matches resume a
+        // right entry from the previous call.
+        let left1: Vec<i32> = (0..60).map(|i| (i * 7) % 13).collect();
+        let left2: Vec<i32> = (0..60).map(|i| (i * 5) % 11).collect();
+        let right1: Vec<i32> = (0..40).map(|i| (i * 3) % 17).collect();
+        let right2: Vec<i32> = (0..40).map(|i| (i * 11) % 7).collect();
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let join1 = JoinPredicate::new(op1, left1.clone(), right1.clone());
+                let join2 = JoinPredicate::new(op2, left2.clone(), right2.clone());
+                let expected = brute_force(&join1, &join2);
+                let out = InequalityJoin::new(join1, join2).collect_sorted();
+                assert!(expected.len() > 200, "{:?} {:?}", op1, op2);
+                assert_eq!(expected, out, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_scan() {
+        // Few lefts among many rights leaves most words of the bitvec empty.
+        let left1 = vec![3, 50, 120];
+        let left2 = vec![90, 10, 60];
+        let right1: Vec<i32> = (0..200).map(|i| (i * 7) % 151).collect();
+        let right2: Vec<i32> = (0..200).map(|i| (i * 11) % 101).collect();
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let join1 = JoinPredicate::new(op1, left1.clone(), right1.clone());
+                let join2 = JoinPredicate::new(op2, left2.clone(), right2.clone());
+                let expected = brute_force(&join1, &join2);
+                let out = InequalityJoin::new(join1, join2).collect_sorted();
+                assert!(!expected.is_empty(), "{:?} {:?}", op1, op2);
+                assert_eq!(expected, out, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    #[test]
+    fn all_equal() {
+        for op1 in OPS {
+            for op2 in OPS {
+                let join1 = JoinPredicate::new(op1, vec![3; 4], vec![3; 5]);
+                let join2 = JoinPredicate::new(op2, vec![7; 4], vec![7; 5]);
+                let out: Vec<_> = InequalityJoin::new(join1, join2).collect();
+
+                let strict = |op| matches!(op, CmpOp::Lt | CmpOp::Gt);
+                let expected = if strict(op1) || strict(op2) { 0 } else { 20 };
+                assert_eq!(expected, out.len(), "{:?} {:?}", op1, op2);
+                assert!(out.iter().all(|m| *m == (3, 7)));
+            }
+        }
+    }
+
+    #[test]
+    fn transform() {
+        // left1 + 5 < right1 and left2 > right2.
+        let (left1, left2) = (vec![4, 1, 7, 3], vec![2, 9, 4, 4]);
+        let (right1, right2) = (vec![5, 12, 8, 10], vec![1, 4, 6, 0]);
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone())
+                .with_left_transform(|v| v + 5),
+            JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone()),
+        );
+        let mut out: Vec<_> = join.into_indices().collect();
+        out.sort_unstable();
+
+        let expected = brute_force(
+            &JoinPredicate::new(
+                CmpOp::Lt,
+                left1.iter().map(|v| v + 5).collect(),
+                right1.clone(),
+            ),
+            &JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone()),
+        );
+        assert_eq!(vec![(0, 3), (1, 1), (1, 2), (1, 3), (3, 3)], expected);
+        assert_eq!(expected, out);
+
+        // Transforming the other side by the inverse gives the same matches.
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1, right1).with_right_transform(|v| v - 5),
+            JoinPredicate::new(CmpOp::Gt, left2, right2),
+        );
+        let mut out: Vec<_> = join.into_indices().collect();
+        out.sort_unstable();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn presorted_input() {
+        let left1 = vec![9, 7, 4, 4, 2];
+        let right1 = vec![8, 5, 4, 1];
+        let left2 = vec![1, 3, 6, 6, 8];
+        let right2 = vec![2, 3, 7, 9];
+
+        let join1 = JoinPredicate::new(CmpOp::LtEq, left1.clone(), right1.clone());
+        let join2 = JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone());
+        let mut expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        expected.sort();
+        assert!(!expected.is_empty());
+
+        // Both predicates need their inputs reversed.
+        let join1 =
+            JoinPredicate::new(CmpOp::LtEq, left1, right1).with_input_order(SortOrder::Desc);
+        let join2 = JoinPredicate::new(CmpOp::Gt, left2, right2).with_input_order(SortOrder::Asc);
+        let mut out: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        out.sort();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn merge_presorted_reverses() {
+        let mut comparisons = 0;
+        let out = merge_presorted(vec![9, 6, 2], vec![8, 7, 3, 1], true, |a, b| {
+            comparisons += 1;
+            a.cmp(b)
+        });
+        assert_eq!(vec![1, 2, 3, 6, 7, 8, 9], out);
+
+        // Linear in the input size. Debug builds also compare within each side
+        // to check it's sorted.
+        assert!(comparisons <= 2 * 6);
+    }
+
+    #[test]
+    fn try_new_errors() {
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3]);
+        let err = InequalityJoin::try_new(join1, join2).unwrap_err();
+        assert_eq!(JoinError::LeftLengthMismatch { join1: 2, join2: 1 }, err);
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3, 4]);
+        let err = InequalityJoin::try_new(join1, join2).unwrap_err();
+        assert_eq!(JoinError::RightLengthMismatch { join1: 1, join2: 2 }, err);
+
+        let err = check_union_len(usize::MAX, 1).unwrap_err();
+        assert_eq!(
+            JoinError::IndexOverflow {
+                left: usize::MAX,
+                right: 1
+            },
+            err
+        );
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3]);
+        assert!(InequalityJoin::try_new(join1, join2).is_ok());
+    }
+
+    #[test]
+    fn try_new_with_budget() {
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3], vec![5, 2, 8, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4], vec![1, 4, 6, 0]),
+            )
+        };
+
+        let (join1, join2) = join();
+        let estimated = InequalityJoin::<i32, i32>::estimate_bytes(8);
+        assert_eq!(
+            Err(JoinError::MemoryBudgetExceeded {
+                estimated,
+                budget: 16
+            }),
+            InequalityJoin::try_new_with_budget(join1, join2, 16).map(|_| ())
+        );
+
+        let (join1, join2) = join();
+        let out: Vec<_> = InequalityJoin::try_new_with_budget(join1, join2, 1 << 20)
+            .unwrap()
+            .into_indices()
+            .collect();
+        assert_eq!(6, out.len());
+    }
+
+    #[test]
+    fn packed_indices() {
+        assert_eq!(
+            vec![2, (3 << 32) | 2],
+            simple_join().collect_packed().unwrap()
+        );
+        assert_eq!(
+            (vec![0, 3], vec![2, 2]),
+            simple_join().collect_index_arrays().unwrap()
+        );
+
+        assert!(check_packed_len(4, 4, 4).is_ok());
+        assert_eq!(
+            JoinError::PackedIndexOverflow { left: 5, right: 3 },
+            check_packed_len(5, 3, 4).unwrap_err()
+        );
+        assert_eq!(
+            JoinError::PackedIndexOverflow { left: 3, right: 5 },
+            check_packed_len(3, 5, 4).unwrap_err()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "left length mismatch")]
+    fn new_panics_on_mismatch() {
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![1], vec![3]);
+        let _ = InequalityJoin::new(join1, join2);
+    }
+
+    #[test]
+    fn new_unchecked() {
+        let (join1, join2) = fixture();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert!(!expected.is_empty());
+
+        let (join1, join2) = fixture();
+        let out: Vec<_> = InequalityJoin::new_unchecked(join1, join2).collect();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn count_set_in_range() {
+        let mut bitvec = L1BitVec::default();
+        bitvec.reset_with_size(150);
+        for idx in (0..150).filter(|i| i % 3 == 0 || i % 7 == 0) {
+            bitvec.set(idx);
+        }
+
+        for end in 0..=150 {
+            let naive = (0..end).filter(|&i| bitvec.bitvec[i]).count();
+            assert_eq!(naive, bitvec.count_set_in_range(end), "end: {}", end);
+        }
+    }
+
+    #[test]
+    fn independent_l2() {
+        let (left1, right1) = (vec![4, 1, 7, 3, 3, 1], vec![5, 2, 8, 3, 1]);
+        let (left2, right2) = (vec![2, 9, 4, 4, 0, 2], vec![1, 4, 6, 0, 2]);
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let (perms, l1) =
+                    L1Union::from_predicate(JoinPredicate::new(op1, left1.clone(), right1.clone()));
+                let l2 = L2Union::from_predicate(
+                    JoinPredicate::new(op2, left2.clone(), right2.clone()),
+                    perms,
+                );
+                l2.assert_agrees_independent(&l1, &left2, &right2);
+            }
+        }
+
+        // Presorted inputs are merged rather than sorted.
+        let (left, right) = (vec![1, 2, 2, 5], vec![0, 2, 4, 4]);
+        for op in OPS {
+            let join = || {
+                JoinPredicate::new(op, left.clone(), right.clone()).with_input_order(SortOrder::Asc)
+            };
+            let (perms, l1) = L1Union::from_predicate(join());
+            let l2 = L2Union::from_predicate(join(), perms);
+            l2.assert_agrees_independent(&l1, &left, &right);
+        }
+    }
+
+    #[test]
+    fn valid_permutation() {
+        debug_assert_permutation(&[2, 0, 3, 1]);
+        debug_assert_permutation(&[]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "permutation index repeated")]
+    fn corrupted_permutation() {
+        let order = JoinPredicate::<i32>::new(CmpOp::Lt, vec![], vec![]).key_order();
+        let _ = L2Union::from_sorted(vec![(1, 0), (2, 1), (3, 2)], &order, vec![0, 0, 2]);
+    }
+
+    #[test]
+    fn tie_break() {
+        let join = |op| {
+            (
+                JoinPredicate::new(op, vec![5, 3], vec![5, 6]),
+                JoinPredicate::new(CmpOp::Lt, vec![0, 9], vec![1, 2]),
+            )
+        };
+
+        let (join1, join2) = join(CmpOp::Lt);
+        let out: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert_eq!(vec![(5, 2)], out);
+
+        let (join1, join2) = join(CmpOp::Lt);
+        let out: Vec<_> =
+            InequalityJoin::with_tie_break(join1, join2, TieBreak::LeftFirst).collect();
+        assert_eq!(vec![(5, 1), (5, 2)], out);
+
+        let (join1, join2) = join(CmpOp::LtEq);
+        let out: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert_eq!(vec![(5, 1), (5, 2)], out);
+
+        let (join1, join2) = join(CmpOp::LtEq);
+        let out: Vec<_> =
+            InequalityJoin::with_tie_break(join1, join2, TieBreak::RightFirst).collect();
+        assert_eq!(vec![(5, 2)], out);
+    }
+
+    #[test]
+    fn independent_tie_breaks() {
+        // Strict first predicate and non-strict second, with ties in both.
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, vec![1, 2, 3], vec![2, 3, 3]),
+                JoinPredicate::new(CmpOp::LtEq, vec![5, 4, 6], vec![5, 4, 7]),
+            )
+        };
+        let collect = |join: InequalityJoin<i32, i32>| {
+            let mut out: Vec<_> = join.into_indices().collect();
+            out.sort_unstable();
+            out
+        };
+
+        let (join1, join2) = join();
+        let expected = collect(InequalityJoin::new(join1, join2));
+        assert_eq!(vec![(0, 0), (0, 2), (1, 1), (1, 2)], expected);
+
+        let (join1, join2) = join();
+        let out = collect(InequalityJoin::with_tie_breaks(
+            join1,
+            join2,
+            TieBreak::RightFirst,
+            TieBreak::LeftFirst,
+        ));
+        assert_eq!(expected, out);
+
+        // A single tie-break for both predicates gets one of them wrong.
+        for tie in [TieBreak::LeftFirst, TieBreak::RightFirst] {
+            let (join1, join2) = join();
+            let out = collect(InequalityJoin::with_tie_break(join1, join2, tie));
+            assert_ne!(expected, out, "{:?}", tie);
+        }
+    }
+
+    #[test]
+    fn grouped_refs() {
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 4, 2, 9], vec![3, 5, 6]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![0, 0, 7, 0], vec![8, 1, 2]);
+
+        let grouped = InequalityJoin::new(join1, join2).grouped_refs();
+        let out: Vec<_> = grouped.iter().collect();
+        assert_eq!(
+            vec![(&1, &[1, 2, 8][..]), (&4, &[1, 2][..]), (&2, &[8][..])],
+            out
+        );
+
+        // Groups are consecutive slices of one buffer.
+        for w in out.windows(2) {
+            let end = w[0].1.as_ptr_range().end;
+            assert_eq!(end, w[1].1.as_ptr());
+        }
+    }
+
+    #[test]
+    fn group_by_left_sorted() {
+        // Ord, but not Hash.
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+        struct Key(i32);
+
+        let key = |v: Vec<i32>| v.into_iter().map(Key).collect::<Vec<_>>();
+        let join1 = JoinPredicate::new(CmpOp::Lt, key(vec![4, 1, 2, 9, 1]), key(vec![3, 5, 6]));
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![0, 0, 7, 0, 5], vec![8, 1, 2]);
+
+        let grouped = InequalityJoin::new(join1, join2).group_by_left_sorted();
+        let mut sorted_rights = grouped.clone();
+        for (_, rights) in sorted_rights.iter_mut() {
+            rights.sort_unstable();
+        }
+        assert_eq!(
+            vec![
+                (Key(1), vec![1, 2, 8, 8]),
+                (Key(2), vec![8]),
+                (Key(4), vec![1, 2]),
+            ],
+            sorted_rights
+        );
+    }
+
+    #[test]
+    fn indices() {
+        let (join1, join2) = simple_fixture();
+        let expected = vec![(0, 2), (3, 2)];
+
+        let out: Vec<_> = InequalityJoin::new(join1, join2).into_indices().collect();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn collect_indices_presized() {
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3, 3, 0], vec![5, 2, 8, 3, 9]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0, 7], vec![1, 4, 6, 0, 3]),
+            )
+        };
+
+        let (expected_left, expected_right): (Vec<_>, Vec<_>) = join().into_indices().collect();
+        assert!(expected_left.len() > 2);
+
+        let (left, right) = join().collect_indices_presized();
+        assert_eq!(expected_left, left);
+        assert_eq!(expected_right, right);
+        assert_eq!(left.len(), left.capacity());
+        assert_eq!(right.len(), right.capacity());
+
+        // Only the remaining matches are counted after partially scanning.
+        let mut join = join();
+        join.next();
+        join.next();
+        let (left, right) = join.collect_indices_presized();
+        assert_eq!(&expected_left[2..], &left[..]);
+        assert_eq!(&expected_right[2..], &right[..]);
+        assert_eq!(left.len(), left.capacity());
+    }
+
+    #[test]
+    fn left_outer() {
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3, 3, 0], vec![5, 2, 8, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0, 7], vec![1, 4, 6, 0]),
+            )
+        };
+
+        let matches: Vec<_> = join().into_indices().collect();
+        let out: Vec<_> = join().left_outer().collect();
+
+        // Matches come first, in scan order, followed by the unmatched rows.
+        let (matched, unmatched) = out.split_at(matches.len());
+        let expected: Vec<_> = matches.iter().map(|&(l, r)| (l, Some(r))).collect();
+        assert_eq!(expected, matched);
+        assert_eq!(&[(2, None), (4, None)], unmatched);
+
+        // Every left row appears, and unmatched rows appear only once.
+        let mut lefts: Vec<_> = out.iter().map(|&(l, _)| l).collect();
+        lefts.sort_unstable();
+        lefts.dedup();
+        assert_eq!((0..6).collect::<Vec<_>>(), lefts);
+        assert!(matches.iter().all(|&(l, _)| l != 2 && l != 4));
+    }
+
+    #[test]
+    fn scan_unions() {
+        let expected: Vec<_> = fixture_join().into_indices().collect();
+        assert!(!expected.is_empty());
+
+        let (join1, join2) = fixture();
+        let (perms, l1) = L1Union::from_predicate(join1);
+        let l2 = L2Union::from_predicate(join2, perms);
+        let out: Vec<_> = scan(&l1, &l2).collect();
+        assert_eq!(expected, out);
+
+        // The unions aren't consumed by scanning.
+        assert_eq!(expected, scan(&l1, &l2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn dedup_by_index() {
+        let left = vec![1, 1, 2, 2, 2, 1, 3];
+        let right = vec![2, 2, 1, 2, 3, 3];
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::LtEq, left.clone(), right.clone()),
+                JoinPredicate::new(CmpOp::GtEq, left.clone(), right.clone()),
+            )
+        };
+
+        let out: Vec<_> = join().dedup_by_index().collect();
+        assert_eq!(join().into_indices().collect::<Vec<_>>(), out);
+
+        let mut unique = out.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(out.len(), unique.len());
+
+        // Every pair of equal keys matches.
+        let expected = left
+            .iter()
+            .map(|l| right.iter().filter(|r| *r == l).count())
+            .sum::<usize>();
+        assert_eq!(expected, out.len());
+    }
+
+    #[test]
+    fn into_ranked() {
+        let (join1, join2) = fixture();
+        let (left1, right1, left2, right2) = (join1.left, join1.right, join2.left, join2.right);
+
+        // Lt sorts L1 ascending, GtEq sorts L2 descending.
+        let mut sorted1: Vec<_> = left1.iter().chain(&right1).copied().collect();
+        sorted1.sort_unstable();
+        let mut sorted2: Vec<_> = left2.iter().chain(&right2).copied().collect();
+        sorted2.sort_unstable_by(|a, b| b.cmp(a));
+
+        let out: Vec<_> = fixture_join().into_ranked().collect();
+        let indices: Vec<_> = fixture_join().into_indices().collect();
+        assert_eq!(
+            indices,
+            out.iter().map(|&(l, r, _, _)| (l, r)).collect::<Vec<_>>()
+        );
+        for (l, r, left_rank, right_rank) in out {
+            assert_eq!(left1[l], sorted1[left_rank]);
+            assert_eq!(right2[r], sorted2[right_rank]);
+        }
+    }
+
+    #[test]
+    fn into_ordinals() {
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3], vec![5, 2, 8, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4], vec![1, 4, 6, 0]),
+            )
+        };
+        let indices: Vec<_> = join().into_indices().collect();
+        let out: Vec<_> = join().into_ordinals().collect();
+        assert_eq!(indices.len(), out.len());
+        for (i, (&(ordinal, left_idx, right_idx), &pair)) in out.iter().zip(&indices).enumerate() {
+            assert_eq!(i, ordinal);
+            assert_eq!(pair, (left_idx, right_idx));
+        }
+    }
+
+    #[test]
+    fn filter_pairs() {
+        let names = ["a", "b", "a", "b", "a"];
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3, 3], vec![5, 2, 8, 3, 6]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0], vec![1, 4, 6, 0, 2]),
+            )
+        };
+
+        let all: Vec<_> = join().into_indices().collect();
+        let out: Vec<_> = join().filter_pairs(|l, r| names[l] == names[r]).collect();
+
+        let expected: Vec<_> = all
+            .iter()
+            .copied()
+            .filter(|&(l, r)| names[l] == names[r])
+            .collect();
+        assert!(expected.len() < all.len());
+        assert!(!expected.is_empty());
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn collect_sorted() {
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::LtEq, vec![3, 1, 3, 2, 1], vec![3, 1, 2, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 2, 1, 2, 3], vec![2, 1, 2, 1]),
+            )
+        };
+
+        let out = join().collect_sorted();
+        assert!(out.windows(2).all(|w| w[0] < w[1]));
+        for _ in 0..4 {
+            assert_eq!(out, join().collect_sorted());
+        }
+    }
+
+    #[test]
+    fn collect_ranges() {
+        // Left 0 matches rights 1 to 3, left 1 matches rights 0, 2 and 3.
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, vec![2, 0, 9], vec![1, 3, 4, 5]),
+            JoinPredicate::new(CmpOp::Gt, vec![9, 5, 9], vec![0, 7, 1, 2]),
+        );
+        let out = join.collect_ranges();
+        assert_eq!(vec![(0, 1..4), (1, 0..1), (1, 2..4)], out);
+    }
+
+    #[test]
+    fn collect_sorted_by() {
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::LtEq, vec![3, 1, 3, 2, 1], vec![3, 1, 2, 3]),
+            JoinPredicate::new(CmpOp::GtEq, vec![2, 2, 1, 2, 3], vec![2, 1, 2, 1]),
+        );
+
+        let dist = |&(l, r): &(usize, usize)| r as i64 - l as i64;
+        let out = join.collect_sorted_by(|a, b| dist(a).cmp(&dist(b)).then(a.cmp(b)));
+        assert_eq!(
+            vec![
+                (4, 0),
+                (3, 0),
+                (4, 1),
+                (4, 2),
+                (1, 0),
+                (3, 2),
+                (4, 3),
+                (0, 0),
+                (1, 1),
+                (3, 3),
+                (1, 2),
+                (2, 3),
+                (1, 3),
+                (0, 3)
+            ],
+            out
+        );
+    }
+
+    #[test]
+    fn collect_sorted_with_ties() {
+        // Every pair ties on both keys.
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, vec![1, 1, 1], vec![2, 2]),
+            JoinPredicate::new(CmpOp::Gt, vec![5, 5, 5], vec![3, 3]),
+        );
+        let out = join.collect_sorted_with_ties(&["c", "a", "b"], &[1, 0]);
+        assert_eq!(vec![(1, 1), (1, 0), (2, 1), (2, 0), (0, 1), (0, 0)], out);
+
+        // Primary keys order first.
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, vec![1, 0, 1], vec![2, 2]),
+            JoinPredicate::new(CmpOp::Gt, vec![5, 5, 5], vec![4, 3]),
+        );
+        let out = join.collect_sorted_with_ties(&[2, 1, 0], &[0, 1]);
+        assert_eq!(vec![(1, 1), (1, 0), (2, 1), (0, 1), (2, 0), (0, 0)], out);
+    }
+
+    #[test]
+    fn split_at() {
+        let expected: Vec<_> = fixture_join().into_indices().collect();
+
+        for mid in 0..=10 {
+            let (first, second) = fixture_join().split_at(mid);
+            let out: Vec<_> = first.chain(second).collect();
+            assert_eq!(expected, out, "mid: {}", mid);
+        }
+    }
+
+    #[test]
+    fn unmatched_lefts() {
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 9, 3, 3, 0], vec![5, 2, 8, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0, 7], vec![1, 4, 6, 0]),
+            )
+        };
+
+        let mut expected: Vec<_> = join()
+            .left_outer()
+            .filter_map(|(l, r)| r.is_none().then_some(l))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(vec![2, 4], expected);
+
+        let mut unmatched = join().unmatched_lefts();
+        let first = unmatched.next().unwrap();
+        assert!(expected.contains(&first));
+        // Left 2 has a key greater than every right, so it's known to be
+        // unmatched before the scan is done.
+        assert!(unmatched.l2_idx < unmatched.unions.1.values.len());
+
+        let mut out: Vec<_> = std::iter::once(first).chain(unmatched).collect();
+        out.sort_unstable();
+        assert_eq!(expected, out);
+
+        for op1 in OPS {
+            for op2 in OPS {
+                let join = || {
+                    InequalityJoin::new(
+                        JoinPredicate::new(op1, vec![1, 5, 9, 3, 7, 5], vec![8, 2, 6, 4, 5]),
+                        JoinPredicate::new(op2, vec![6, 2, 8, 0, 4, 6], vec![1, 7, 3, 9, 5]),
+                    )
+                };
+                let mut expected: Vec<_> = join()
+                    .left_outer()
+                    .filter_map(|(l, r)| r.is_none().then_some(l))
+                    .collect();
+                expected.sort_unstable();
+                let mut out: Vec<_> = join().unmatched_lefts().collect();
+                out.sort_unstable();
+                assert_eq!(expected, out, "{:?} {:?}", op1, op2);
+            }
+        }
+    }
+
+    #[test]
+    fn for_each_match() {
+        let (join1, join2) = simple_fixture();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).into_indices().collect();
+
+        let (join1, join2) = simple_fixture();
+        let mut out = Vec::new();
+        InequalityJoin::new(join1, join2).for_each_match(|l, r| out.push((l, r)));
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn first_left_per_right() {
+        let left1 = vec![3, 1, 4, 1, 5, 9, 2, 6];
+        let right1 = vec![5, 3, 5, 8, 9, 7, 1];
+        let left2 = vec![2, 7, 1, 8, 2, 8, 1, 8];
+        let right2 = vec![2, 8, 1, 8, 2, 8, 4];
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+            JoinPredicate::new(CmpOp::LtEq, left2.clone(), right2.clone()),
+        );
+        let mut out: Vec<_> = join.first_left_per_right().collect();
+        out.sort_unstable_by_key(|&(_, r)| r);
+
+        let expected: Vec<_> = (0..right1.len())
+            .filter_map(|r| {
+                (0..left1.len())
+                    .find(|&l| left1[l] < right1[r] && left2[l] <= right2[r])
+                    .map(|l| (l, r))
+            })
+            .collect();
+        assert_eq!(
+            vec![(0, 0), (1, 1), (2, 2), (0, 3), (0, 4), (0, 5)],
+            expected
+        );
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn map_payloads() {
+        let left = ["a", "b", "c", "d"];
+        let right = ["w", "x", "y", "z"];
+
+        let out: Vec<_> = simple_join()
+            .into_indices()
+            .map_payloads(&left, &right)
+            .collect();
+        assert_eq!(vec![(&"a", &"y"), (&"d", &"y")], out);
+        assert!(std::ptr::eq(out[0].0, &left[0]));
+        assert!(std::ptr::eq(out[0].1, &right[2]));
+
+        // Payloads are only looked up as matches are yielded, so the second
+        // match's out of bounds left position isn't touched.
+        let mut lazy = simple_join()
+            .into_indices()
+            .map_payloads(&left[..1], &right);
+        assert_eq!(Some((&"a", &"y")), lazy.next());
+    }
+
+    #[test]
+    fn probe() {
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 4, 2, 9], vec![]);
+        let join2 = JoinPredicate::new(CmpOp::GtEq, vec![5, 0, 7, 3], vec![]);
+        let join = InequalityJoin::new(join1, join2);
+
+        assert_eq!(vec![0, 2], join.probe(3, 5));
+        assert_eq!(vec![0, 1, 2], join.probe(5, 0));
+        assert_eq!(vec![2], join.probe(10, 6));
+        assert_eq!(Vec::<usize>::new(), join.probe(1, 0));
+        assert_eq!(Vec::<usize>::new(), join.probe(10, 8));
+    }
+
+    #[test]
+    fn against_scalar() {
+        let left1 = vec![4, 1, 7, 3, 3, 9];
+        let left2 = vec![2, 9, 4, 4, 0, 7];
+
+        let out: Vec<_> = InequalityJoin::against_scalar(
+            CmpOp::Gt,
+            CmpOp::LtEq,
+            left1.clone(),
+            left2.clone(),
+            3,
+            4,
+        )
+        .collect();
+        assert_eq!(vec![0, 2], out);
+
+        // Same as a join against a single right row.
+        let expected: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Gt, left1, vec![3]),
+            JoinPredicate::new(CmpOp::LtEq, left2, vec![4]),
+        )
+        .collect_sorted()
+        .into_iter()
+        .map(|(l, _)| l)
+        .collect();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn explain_pair() {
+        let join = simple_join();
+
+        let explained = join.explain_pair(0, 2).unwrap();
+        assert!(explained.matches());
+        assert_eq!(
+            PredicateResult {
+                op: CmpOp::Gt,
+                left: &100,
+                right: &80,
+                holds: true
+            },
+            explained.pred1
+        );
+        assert_eq!(
+            PredicateResult {
+                op: CmpOp::Lt,
+                left: &6,
+                right: &10,
+                holds: true
+            },
+            explained.pred2
+        );
+
+        let explained = join.explain_pair(1, 2).unwrap();
+        assert!(!explained.matches());
+        assert!(explained.pred1.holds);
+        assert!(!explained.pred2.holds);
+        assert_eq!((&11, &10), (explained.pred2.left, explained.pred2.right));
+
+        assert!(join.explain_pair(4, 0).is_none());
+    }
+
+    #[test]
+    fn remove_right() {
+        let all = fixture_join().collect_sorted();
+
+        let mut iejoin = fixture_join();
+        let removed = iejoin.remove_right(0);
+        let expected: Vec<_> = all.iter().copied().filter(|&(_, r)| r == 0).collect();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, removed);
+        assert_eq!(3, iejoin.right_len());
+
+        // Already removed.
+        assert!(iejoin.remove_right(0).is_empty());
+
+        let remaining: Vec<_> = all.iter().copied().filter(|&(_, r)| r != 0).collect();
+        assert_eq!(remaining, iejoin.collect_sorted());
+    }
+
+    #[test]
+    fn remove_right_keeps_positions() {
+        let removed = || {
+            let mut iejoin = fixture_join();
+            assert!(!iejoin.remove_right(0).is_empty());
+            iejoin
+        };
+
+        let mut expected = fixture_join().adjacency();
+        for row in &mut expected {
+            row.set(0, false);
+        }
+        let rows = removed().adjacency();
+        assert!(rows.iter().all(|row| row.len() == 4));
+        assert_eq!(expected, rows);
+
+        let (offsets, rights) = removed().to_csr();
+        let from_csr: Vec<_> = offsets
+            .windows(2)
+            .enumerate()
+            .flat_map(|(l, row)| rights[row[0]..row[1]].iter().map(move |&r| (l, r)))
+            .collect();
+        let remaining: Vec<_> = fixture_join()
+            .collect_sorted()
+            .into_iter()
+            .filter(|&(_, r)| r != 0)
+            .collect();
+        assert_eq!(remaining, from_csr);
+
+        let packed = removed().collect_packed().unwrap();
+        assert_eq!(remaining.len(), packed.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "snapshot is from a different join")]
+    fn snapshot_before_remove_right() {
+        let mut iejoin = fixture_join();
+        let snapshot = iejoin.snapshot();
+        iejoin.remove_right(0);
+        let _ = iejoin.resume_from(snapshot);
+    }
+
+    #[test]
+    fn join_ext() {
+        let rows = vec![(100, 6), (140, 11), (80, 10), (90, 5)];
         let expected = vec![(100, 10), (90, 10)];
 
-        let iejoin = InequalityJoin::new(join1, join2);
-        let out: Vec<_> = iejoin.into_iter().collect();
+        let out: Vec<_> = rows
+            .iter()
+            .copied()
+            .inequality_join(CmpOp::Gt, CmpOp::Lt, rows.clone())
+            .collect();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn collect_into() {
+        let mut out = Vec::with_capacity(16);
+        let ptr = out.as_ptr();
+
+        let (join1, join2) = simple_fixture();
+        InequalityJoin::new(join1, join2).collect_into(&mut out);
+        assert_eq!(vec![(100, 10), (90, 10)], out);
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![1, 5], vec![3]);
+        InequalityJoin::new(join1, join2).collect_into(&mut out);
+        assert_eq!(vec![(1, 3)], out);
+
+        assert_eq!(ptr, out.as_ptr());
+    }
+
+    #[test]
+    fn chunks() {
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, vec![1, 2, 3, 4, 5], vec![3, 5, 1, 6, 2]),
+                JoinPredicate::new(CmpOp::LtEq, vec![4, 1, 5, 2, 3], vec![2, 6, 4, 1, 3]),
+            )
+        };
+
+        let (join1, join2) = join();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert_eq!(6, expected.len());
+
+        let (join1, join2) = join();
+        let chunks: Vec<_> = InequalityJoin::new(join1, join2).chunks(4).collect();
+        let sizes: Vec<_> = chunks.iter().map(|c| c.len()).collect();
+        assert_eq!(vec![4, 2], sizes);
+        assert_eq!(expected, chunks.concat());
+    }
+
+    #[test]
+    fn self_join_unique() {
+        let values = vec![3, 1, 4, 1, 5];
+
+        let mut out: Vec<_> =
+            InequalityJoin::self_join_unique(CmpOp::LtEq, values.clone()).collect();
+        out.sort();
+
+        let expected: Vec<_> = (0..values.len())
+            .flat_map(|i| (i + 1..values.len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| values[i] <= values[j])
+            .collect();
+        assert_eq!(
+            vec![(0, 2), (0, 4), (1, 2), (1, 3), (1, 4), (2, 4), (3, 4)],
+            expected
+        );
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn conjunction() {
+        let (a, b, c, d) = (
+            vec![4, 1, 7, 3, 3],
+            vec![2, 9, 4, 4, 0],
+            vec![1, 1, 2, 2, 3],
+            vec![5, 0, 5, 0, 5],
+        );
+        let (w, x, y, z) = (
+            vec![5, 2, 8, 3],
+            vec![1, 4, 6, 0],
+            vec![2, 2, 1, 3],
+            vec![0, 5, 5, 0],
+        );
+
+        let mut out: Vec<_> = InequalityJoin::conjunction(&[
+            (CmpOp::Lt, &a, &w),
+            (CmpOp::GtEq, &b, &x),
+            (CmpOp::LtEq, &c, &y),
+            (CmpOp::Gt, &d, &z),
+        ])
+        .collect();
+        out.sort_unstable();
+
+        let mut expected = Vec::new();
+        for l in 0..a.len() {
+            for r in 0..w.len() {
+                if a[l] < w[r] && b[l] >= x[r] && c[l] <= y[r] && d[l] > z[r] {
+                    expected.push((l, r));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(expected, out);
+
+        let mut from_array: Vec<_> = InequalityJoin::conjunction_array([
+            JoinPredicate::new(CmpOp::Lt, a.clone(), w.clone()),
+            JoinPredicate::new(CmpOp::GtEq, b.clone(), x.clone()),
+            JoinPredicate::new(CmpOp::LtEq, c.clone(), y.clone()),
+        ])
+        .collect();
+        from_array.sort_unstable();
+        let mut three: Vec<_> = InequalityJoin::conjunction(&[
+            (CmpOp::Lt, &a, &w),
+            (CmpOp::GtEq, &b, &x),
+            (CmpOp::LtEq, &c, &y),
+        ])
+        .collect();
+        three.sort_unstable();
+        assert_eq!(three, from_array);
+        assert!(from_array.len() >= expected.len());
+
+        // The residuals remove some of the first two predicates' matches.
+        let all = InequalityJoin::conjunction(&[(CmpOp::Lt, &a, &w), (CmpOp::GtEq, &b, &x)]);
+        assert!(all.count() > expected.len());
+    }
+
+    #[test]
+    fn single() {
+        let left = vec![4, 1, 7, 3, 3];
+        let right = vec![3, 8, 2, 5];
+
+        let join1 = JoinPredicate::new(CmpOp::GtEq, left.clone(), right.clone());
+        let join2 = JoinPredicate::new(CmpOp::LtEq, vec![0; 5], vec![0; 4]);
+        let mut expected: Vec<_> = InequalityJoin::new(join1, join2).into_indices().collect();
+        expected.sort();
+
+        let mut out: Vec<_> = InequalityJoin::single(CmpOp::GtEq, left, right)
+            .into_indices()
+            .collect();
+        out.sort();
+
+        assert_eq!(
+            vec![
+                (0, 0),
+                (0, 2),
+                (2, 0),
+                (2, 2),
+                (2, 3),
+                (3, 0),
+                (3, 2),
+                (4, 0),
+                (4, 2)
+            ],
+            out
+        );
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn snapshot() {
+        let expected: Vec<_> = fixture_join().into_indices().collect();
+        assert!(expected.len() > 2);
+
+        for mid in 0..=expected.len() {
+            let mut first = fixture_join().into_indices();
+            let mut out: Vec<_> = first.by_ref().take(mid).collect();
+            let snapshot = first.join.snapshot();
+            drop(first);
+
+            out.extend(fixture_join().resume_from(snapshot).into_indices());
+            assert_eq!(expected, out, "mid: {}", mid);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "snapshot is from a different join")]
+    fn snapshot_other_join() {
+        let join = |op| {
+            let (join1, join2) = fixture_with(CmpOp::Lt, op);
+            InequalityJoin::new(join1, join2)
+        };
+        let snapshot = join(CmpOp::GtEq).snapshot();
+        join(CmpOp::Gt).resume_from(snapshot);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let a = JoinPredicate::new(CmpOp::Lt, vec![1, 2, 3], vec![4, 5]);
+        let b = JoinPredicate::new(CmpOp::Lt, vec![9, 8, 7], vec![6, 5]);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let c = JoinPredicate::new(CmpOp::LtEq, vec![1, 2, 3], vec![4, 5]);
+        assert_ne!(a.fingerprint(), c.fingerprint());
+
+        let d = JoinPredicate::new(CmpOp::Lt, vec![1, 2], vec![3, 4, 5]);
+        assert_ne!(a.fingerprint(), d.fingerprint());
+    }
+
+    #[test]
+    fn prepare_start() {
+        let (join1, join2) = fixture();
+        let prepared = InequalityJoin::prepare(join1, join2).unwrap();
+        assert_eq!(5, prepared.left_len());
+        assert_eq!(4, prepared.right_len());
+        assert_eq!(2, prepared.bitvec_bytes());
+        assert_eq!(Some((&1, &8)), prepared.key1_range());
+        assert_eq!(Some((&0, &9)), prepared.key2_range());
+
+        let (join1, join2) = fixture();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        let out: Vec<_> = InequalityJoin::start(prepared).collect();
         assert_eq!(expected, out);
+
+        let (join1, _) = fixture();
+        let mismatched = JoinPredicate::new(CmpOp::Lt, vec![1], vec![2, 3, 4, 5]);
+        assert!(InequalityJoin::prepare(join1, mismatched).is_err());
+    }
+
+    #[test]
+    fn reuse_scratch() {
+        let (join1, join2) = simple_fixture();
+        let mut join = InequalityJoin::with_scratch(join1, join2, JoinScratch::default());
+        assert_eq!(vec![(100, 10), (90, 10)], join.by_ref().collect::<Vec<_>>());
+
+        let scratch = join.into_scratch();
+        let ptr = scratch.bitvec.bitvec.as_raw_slice().as_ptr();
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2], vec![3]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![5, 1], vec![3]);
+        let join = InequalityJoin::with_scratch(join1, join2, scratch);
+        assert_eq!(3, join.bitvec.bitvec.len());
+        assert!(join.bitvec.bitvec.not_any());
+        assert_eq!(ptr, join.bitvec.bitvec.as_raw_slice().as_ptr());
+        assert_eq!(vec![(2, 3)], join.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stops_after_last_right() {
+        // Left keys 10 and 11 sort after every right key in L2.
+        let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2, 3, 4], vec![5, 6]);
+        let join2 = JoinPredicate::new(CmpOp::Lt, vec![0, 10, 1, 11], vec![5, 6]);
+        let mut join = InequalityJoin::new(join1, join2);
+
+        let out: Vec<_> = join.by_ref().collect();
+        assert_eq!(vec![(1, 5), (3, 5), (1, 6), (3, 6)], out);
+        assert_eq!(4, join.l2_idx);
+        assert_eq!(6, join.l2.values.len());
+        assert_eq!(2, join.bitvec.bitvec.count_ones());
+    }
+
+    #[test]
+    fn half_open_interval() {
+        let points = vec![10, 20, 15, 5];
+        let starts = vec![10, 15];
+        let ends = vec![20, 30];
+
+        let mut out: Vec<_> = InequalityJoin::half_open_interval(points, starts, ends)
+            .into_indices()
+            .collect();
+        out.sort();
+
+        // 10 == start of the first interval is included, 20 == its end is not.
+        assert_eq!(vec![(0, 0), (1, 1), (2, 0), (2, 1)], out);
+    }
+
+    #[test]
+    fn interval() {
+        // Points on both ends of [10, 20] and inside it.
+        let points = vec![10, 15, 20, 5];
+        let join = |start, end| {
+            let mut out: Vec<_> =
+                InequalityJoin::interval(points.clone(), vec![10], vec![20], start, end)
+                    .into_indices()
+                    .map(|(l, _)| l)
+                    .collect();
+            out.sort();
+            out
+        };
+
+        assert_eq!(vec![0, 1], join(BoundOp::Closed, BoundOp::Open));
+        assert_eq!(vec![1, 2], join(BoundOp::Open, BoundOp::Closed));
+        assert_eq!(vec![0, 1, 2], join(BoundOp::Closed, BoundOp::Closed));
+        assert_eq!(vec![1], join(BoundOp::Open, BoundOp::Open));
+    }
+
+    #[test]
+    fn from_ranges() {
+        let ranges = vec![10..20, 15..30, 40..40];
+        let key = RangeKey::new(vec![10, 20, 15, 5, 40], ranges.clone());
+
+        let out = InequalityJoin::from_ranges(key).collect_sorted();
+        assert_eq!(vec![(0, 0), (1, 1), (2, 0), (2, 1)], out);
+
+        let points = [10, 20, 15, 5, 40];
+        for (p, r) in out {
+            assert!(ranges[r].contains(&points[p]));
+        }
+    }
+
+    #[test]
+    fn extremum_per_left() {
+        let out: Vec<_> = simple_join().extremum_per_left(ExtremumKind::Max).collect();
+        assert_eq!(
+            vec![(0, Some(10)), (1, None), (2, None), (3, Some(10))],
+            out
+        );
+
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3], vec![5, 2, 8, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4], vec![1, 4, 6, 0]),
+            )
+        };
+        let max: Vec<_> = join().extremum_per_left(ExtremumKind::Max).collect();
+        let min: Vec<_> = join().extremum_per_left(ExtremumKind::Min).collect();
+        assert_eq!(
+            vec![(0, Some(1)), (1, Some(6)), (2, None), (3, Some(1))],
+            max
+        );
+        assert_eq!(
+            vec![(0, Some(1)), (1, Some(0)), (2, None), (3, Some(1))],
+            min
+        );
+    }
+
+    #[test]
+    fn asof() {
+        // Trades joined with the quotes at or before them, at most 10 behind.
+        let trades = vec![105, 100, 121, 95, 130];
+        let quotes = vec![90, 100, 104, 110, 118, 104];
+        let join = || {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::GtEq, trades.clone(), quotes.clone()),
+                JoinPredicate::new(
+                    CmpOp::Lt,
+                    trades.clone(),
+                    quotes.iter().map(|q| q + 10).collect(),
+                ),
+            )
+        };
+
+        let backward: Vec<_> = join().asof(AsofDirection::Backward).collect();
+        assert_eq!(
+            vec![
+                (0, Some(2)),
+                (1, Some(1)),
+                (2, Some(4)),
+                (3, Some(0)),
+                (4, None)
+            ],
+            backward
+        );
+        let forward: Vec<_> = join().asof(AsofDirection::Forward).collect();
+        assert_eq!(
+            vec![
+                (0, Some(1)),
+                (1, Some(1)),
+                (2, Some(4)),
+                (3, Some(0)),
+                (4, None)
+            ],
+            forward
+        );
+    }
+
+    #[test]
+    fn left_counts() {
+        let (join1, join2) = simple_fixture();
+
+        let out: Vec<_> = InequalityJoin::new(join1, join2).left_counts().collect();
+        assert_eq!(vec![(90, 1), (100, 1), (80, 0), (140, 0)], out);
+    }
+
+    #[test]
+    fn sorted_by_right() {
+        for op in [CmpOp::Lt, CmpOp::LtEq, CmpOp::Gt, CmpOp::GtEq] {
+            let join1 = JoinPredicate::new(CmpOp::Lt, vec![1, 2, 3, 4, 5], vec![3, 5, 1, 6, 2]);
+            let join2 = JoinPredicate::new(op, vec![4, 1, 5, 2, 3], vec![2, 6, 4, 1, 3]);
+
+            let out: Vec<_> = InequalityJoin::new(join1, join2)
+                .sorted_by_right()
+                .collect();
+            assert!(!out.is_empty());
+            assert!(
+                out.windows(2).all(|w| w[0].1 <= w[1].1),
+                "{:?}: {:?}",
+                op,
+                out
+            );
+        }
     }
 }
@@ -0,0 +1,99 @@
+//! Joins over `BTreeMap`s keyed by both join keys.
+use super::{CmpOp, InequalityJoin, InequalityJoinIndices, JoinPredicate, SortOrder};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// Join of two maps keyed by `(key1, key2)`, yielding the values of matching
+/// entries. See [`InequalityJoin::from_btree_maps`].
+#[derive(Debug)]
+pub struct BTreeMapJoin<'a, T1, T2, L, R> {
+    join: InequalityJoinIndices<T1, T2>,
+    left: Vec<&'a L>,
+    right: Vec<&'a R>,
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    /// Join the entries of two maps where `left.0 op1 right.0` and
+    /// `left.1 op2 right.1` for keys `(key1, key2)`, yielding the pairs of
+    /// matching map values.
+    ///
+    /// Map keys iterate in ascending order of the first key, so the first
+    /// union is merged rather than sorted.
+    pub fn from_btree_maps<'a, L, R>(
+        op1: CmpOp,
+        op2: CmpOp,
+        left: &'a BTreeMap<(T1, T2), L>,
+        right: &'a BTreeMap<(T1, T2), R>,
+    ) -> BTreeMapJoin<'a, T1, T2, L, R> {
+        let (left1, left2): (Vec<_>, Vec<_>) = left.keys().cloned().unzip();
+        let (right1, right2): (Vec<_>, Vec<_>) = right.keys().cloned().unzip();
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(op1, left1, right1).with_input_order(SortOrder::Asc),
+            JoinPredicate::new(op2, left2, right2),
+        );
+
+        BTreeMapJoin {
+            join: join.into_indices(),
+            left: left.values().collect(),
+            right: right.values().collect(),
+        }
+    }
+}
+
+impl<'a, T1, T2, L, R> Iterator for BTreeMapJoin<'a, T1, T2, L, R> {
+    type Item = (&'a L, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (left_idx, right_idx) = self.join.next()?;
+        Some((self.left[left_idx], self.right[right_idx]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn btree_maps() {
+        let left: BTreeMap<_, _> = [((5, 1), "a"), ((2, 9), "b"), ((7, 4), "c"), ((2, 3), "d")]
+            .into_iter()
+            .collect();
+        let right: BTreeMap<_, _> = [((6, 5), "w"), ((3, 2), "x"), ((8, 8), "y")]
+            .into_iter()
+            .collect();
+
+        let (left1, left2): (Vec<_>, Vec<_>) = left.keys().copied().unzip();
+        let (right1, right2): (Vec<_>, Vec<_>) = right.keys().copied().unzip();
+        let left_values: Vec<_> = left.values().collect();
+        let right_values: Vec<_> = right.values().collect();
+        let mut expected: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1, right1),
+            JoinPredicate::new(CmpOp::Lt, left2, right2),
+        )
+        .into_indices()
+        .map(|(l, r)| (left_values[l], right_values[r]))
+        .collect();
+        expected.sort();
+
+        let mut out: Vec<_> =
+            InequalityJoin::from_btree_maps(CmpOp::Lt, CmpOp::Lt, &left, &right).collect();
+        out.sort();
+
+        assert_eq!(
+            vec![
+                (&"a", &"w"),
+                (&"a", &"y"),
+                (&"c", &"y"),
+                (&"d", &"w"),
+                (&"d", &"y")
+            ],
+            out
+        );
+        assert_eq!(expected, out);
+    }
+}
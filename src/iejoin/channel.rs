@@ -0,0 +1,72 @@
+//! Producing matches on a separate thread.
+use super::InequalityJoin;
+use std::fmt::Debug;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug + Send + 'static,
+    T2: Ord + Clone + Debug + Send + 'static,
+{
+    /// Run the scan on a new thread, sending matches over a channel holding
+    /// up to `cap` unreceived matches.
+    ///
+    /// The thread blocks while the channel is full, and stops early if the
+    /// receiver is dropped. The channel also closes if the scan panics, e.g.
+    /// in a key's `Clone`, so join the returned handle once the channel is
+    /// drained to tell a complete result from a truncated one.
+    pub fn into_channel(self, cap: usize) -> (Receiver<(T1, T2)>, JoinHandle<()>) {
+        let (tx, rx) = mpsc::sync_channel(cap);
+        let handle = thread::spawn(move || {
+            for pair in self {
+                if tx.send(pair).is_err() {
+                    break;
+                }
+            }
+        });
+        (rx, handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iejoin::tests::fixture_join;
+    use crate::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+
+    #[test]
+    fn into_channel() {
+        let expected: Vec<_> = fixture_join().collect();
+        let (rx, handle) = fixture_join().into_channel(1);
+        let out: Vec<_> = rx.into_iter().collect();
+        assert_eq!(expected, out);
+        handle.join().unwrap();
+
+        // Dropping the receiver stops the producer.
+        let (rx, handle) = fixture_join().into_channel(0);
+        assert_eq!(Some(expected[0]), rx.recv().ok());
+        drop(rx);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn into_channel_panic() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Key(i32);
+
+        impl Clone for Key {
+            fn clone(&self) -> Self {
+                panic!("clone failed")
+            }
+        }
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, vec![Key(1)], vec![Key(2)]),
+            JoinPredicate::new(CmpOp::Lt, vec![Key(1)], vec![Key(2)]),
+        );
+        let (rx, handle) = join.into_channel(1);
+        // The channel just closes, the handle reports the panic.
+        assert_eq!(0, rx.into_iter().count());
+        assert!(handle.join().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Joins over inputs split into chunks.
+use super::{validate_lens, CmpOp, InequalityJoin, JoinScratch, KeyOrder, L1Union, L2Union, Side};
+use std::fmt::Debug;
+
+/// Like [`JoinPredicate`](super::JoinPredicate), but with each side given as
+/// chunks that are logically concatenated.
+#[derive(Debug)]
+pub struct ChunkedPredicate<T> {
+    op: CmpOp,
+    left: Vec<Vec<T>>,
+    right: Vec<Vec<T>>,
+}
+
+impl<T> ChunkedPredicate<T> {
+    pub fn new(op: CmpOp, left: Vec<Vec<T>>, right: Vec<Vec<T>>) -> Self {
+        ChunkedPredicate { op, left, right }
+    }
+
+    fn lens(&self) -> (usize, usize) {
+        let len = |chunks: &[Vec<T>]| chunks.iter().map(Vec::len).sum();
+        (len(&self.left), len(&self.right))
+    }
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Create a join from chunked inputs.
+    ///
+    /// Input positions count across chunks, as if each side's chunks were
+    /// concatenated. The chunks are moved straight into the unions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
+    pub fn from_chunks(join1: ChunkedPredicate<T1>, join2: ChunkedPredicate<T2>) -> Self {
+        let (left_len, right_len) = join1.lens();
+        validate_lens((left_len, right_len), join2.lens()).unwrap_or_else(|e| panic!("{}", e));
+
+        let order1 = KeyOrder::new(join1.op);
+        let order2 = KeyOrder::new(join2.op);
+
+        let mut values1 = Vec::with_capacity(left_len + right_len);
+        values1.extend(
+            join1
+                .left
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(idx, v)| (v, Side::Left, idx)),
+        );
+        values1.extend(
+            join1
+                .right
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(idx, v)| (v, Side::Right, idx)),
+        );
+        values1.sort_unstable_by(|a, b| L1Union::compare(&order1, a, b));
+        let (perms, l1) = L1Union::from_sorted(values1, &order1);
+
+        let mut values2 = Vec::with_capacity(left_len + right_len);
+        values2.extend(
+            join2
+                .left
+                .into_iter()
+                .flatten()
+                .chain(join2.right.into_iter().flatten())
+                .enumerate()
+                .map(|(idx, v)| (v, idx)),
+        );
+        values2.sort_unstable_by(|a, b| L2Union::compare(&order2, left_len, a, b));
+        let l2 = L2Union::from_sorted(values2, &order2, perms);
+
+        Self::from_unions_with_scratch(l1, l2, JoinScratch::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::JoinPredicate;
+
+    #[test]
+    fn from_chunks() {
+        let left1 = vec![vec![4, 1], vec![], vec![7, 3, 3]];
+        let left2 = vec![vec![2], vec![9, 4, 4], vec![0]];
+        let right1 = vec![vec![5], vec![2, 8], vec![3]];
+        let right2 = vec![vec![1, 4, 6], vec![0], vec![]];
+
+        let concat = |chunks: &Vec<Vec<i32>>| chunks.concat();
+        let mut expected: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, concat(&left1), concat(&right1)),
+            JoinPredicate::new(CmpOp::GtEq, concat(&left2), concat(&right2)),
+        )
+        .into_indices()
+        .collect();
+        expected.sort_unstable();
+
+        let mut out: Vec<_> = InequalityJoin::from_chunks(
+            ChunkedPredicate::new(CmpOp::Lt, left1, right1),
+            ChunkedPredicate::new(CmpOp::GtEq, left2, right2),
+        )
+        .into_indices()
+        .collect();
+        out.sort_unstable();
+        assert!(!out.is_empty());
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_chunks_length_mismatch() {
+        InequalityJoin::from_chunks(
+            ChunkedPredicate::new(CmpOp::Lt, vec![vec![1], vec![2]], vec![vec![3]]),
+            ChunkedPredicate::new(CmpOp::Lt, vec![vec![1]], vec![vec![3]]),
+        );
+    }
+}
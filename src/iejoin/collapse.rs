@@ -0,0 +1,128 @@
+//! Joins collapsing duplicate left rows.
+use super::{validate, InequalityJoin, InequalityJoinIndices, JoinPredicate};
+use std::fmt::Debug;
+
+/// Join over distinct left keys, expanded back to every left row. See
+/// [`InequalityJoin::collapse_duplicates`].
+#[derive(Debug)]
+pub struct CollapsedJoin<T1, T2> {
+    join: InequalityJoinIndices<T1, T2>,
+    /// Input positions of left rows, grouped by key.
+    members: Vec<usize>,
+    /// Start of each distinct key's group in `members`, with a trailing end.
+    offsets: Vec<usize>,
+
+    /// Remaining group members for the current match, and its right row.
+    curr: Option<(usize, usize, usize)>,
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Create a join that only joins each distinct left `(key1, key2)` once,
+    /// yielding `(left_idx, right_idx)` for every left row sharing the key.
+    ///
+    /// Worthwhile when left keys repeat often: the unions and scan are sized
+    /// by the number of distinct left keys instead of left rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
+    pub fn collapse_duplicates(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+    ) -> CollapsedJoin<T1, T2> {
+        validate(&join1, &join2).unwrap_or_else(|e| panic!("{}", e));
+
+        let mut rows: Vec<_> = join1
+            .left
+            .into_iter()
+            .zip(join2.left)
+            .enumerate()
+            .map(|(idx, (k1, k2))| (k1, k2, idx))
+            .collect();
+        rows.sort_unstable();
+
+        let mut left1 = Vec::new();
+        let mut left2 = Vec::new();
+        let mut members = Vec::with_capacity(rows.len());
+        let mut offsets = Vec::new();
+        for (k1, k2, idx) in rows {
+            if left1.last() != Some(&k1) || left2.last() != Some(&k2) {
+                offsets.push(members.len());
+                left1.push(k1);
+                left2.push(k2);
+            }
+            members.push(idx);
+        }
+        offsets.push(members.len());
+
+        let join = InequalityJoin::new(
+            JoinPredicate {
+                left: left1,
+                input_order: None,
+                ..join1
+            },
+            JoinPredicate {
+                left: left2,
+                input_order: None,
+                ..join2
+            },
+        );
+
+        CollapsedJoin {
+            join: join.into_indices(),
+            members,
+            offsets,
+            curr: None,
+        }
+    }
+}
+
+impl<T1, T2> Iterator for CollapsedJoin<T1, T2> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((idx, end, right_idx)) = &mut self.curr {
+                if *idx < *end {
+                    *idx += 1;
+                    return Some((self.members[*idx - 1], *right_idx));
+                }
+            }
+
+            let (key_idx, right_idx) = self.join.next()?;
+            self.curr = Some((self.offsets[key_idx], self.offsets[key_idx + 1], right_idx));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::CmpOp;
+
+    #[test]
+    fn collapse_duplicates() {
+        let left1: Vec<_> = (0..40).map(|i| i % 3).collect();
+        let left2: Vec<_> = (0..40).map(|i| (i % 4) * 2).collect();
+        let (right1, right2) = (vec![1, 2, 3, 0], vec![3, 4, 7, 9]);
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+                JoinPredicate::new(CmpOp::LtEq, left2.clone(), right2.clone()),
+            )
+        };
+
+        let (join1, join2) = join();
+        let expected = InequalityJoin::new(join1, join2).collect_sorted();
+        assert!(expected.len() > 40);
+
+        let (join1, join2) = join();
+        let mut out: Vec<_> = InequalityJoin::collapse_duplicates(join1, join2).collect();
+        out.sort_unstable();
+        assert_eq!(expected, out);
+    }
+}
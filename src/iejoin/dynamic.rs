@@ -0,0 +1,98 @@
+//! Keys compared by a runtime comparator, for joining dynamically typed
+//! values.
+use std::any::Any;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Comparator shared by all keys of a join.
+pub type DynCmp = Arc<dyn Fn(&dyn Any, &dyn Any) -> Ordering + Send + Sync>;
+
+/// A dynamically typed key ordered by a comparator.
+///
+/// Keys in the same join must share a comparator that is a total order over
+/// all of their values. Cloning a key only clones the handles.
+#[derive(Clone)]
+pub struct DynKey {
+    value: Arc<dyn Any + Send + Sync>,
+    cmp: DynCmp,
+}
+
+impl DynKey {
+    pub fn new<V: Any + Send + Sync>(value: V, cmp: &DynCmp) -> Self {
+        DynKey {
+            value: Arc::new(value),
+            cmp: cmp.clone(),
+        }
+    }
+
+    pub fn value(&self) -> &dyn Any {
+        self.value.as_ref()
+    }
+}
+
+impl fmt::Debug for DynKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynKey").finish_non_exhaustive()
+    }
+}
+
+impl Ord for DynKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp)(self.value(), other.value())
+    }
+}
+
+impl PartialOrd for DynKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for DynKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DynKey {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+
+    /// Compare integers and floats numerically.
+    fn numeric(a: &dyn Any, b: &dyn Any) -> Ordering {
+        let as_f64 = |v: &dyn Any| match (v.downcast_ref::<i64>(), v.downcast_ref::<f64>()) {
+            (Some(i), _) => *i as f64,
+            (_, Some(f)) => *f,
+            _ => panic!("unsupported key"),
+        };
+        as_f64(a).total_cmp(&as_f64(b))
+    }
+
+    #[test]
+    fn dyn_keys() {
+        let cmp: DynCmp = Arc::new(numeric);
+        let int = |i: i64| DynKey::new(i, &cmp);
+        let float = |f: f64| DynKey::new(f, &cmp);
+
+        let join = InequalityJoin::new(
+            JoinPredicate::new(
+                CmpOp::Lt,
+                vec![int(1), float(2.5)],
+                vec![float(1.5), int(3)],
+            ),
+            JoinPredicate::new(
+                CmpOp::Gt,
+                vec![int(10), float(4.0)],
+                vec![float(9.5), int(5)],
+            ),
+        );
+        let mut out: Vec<_> = join.into_indices().collect();
+        out.sort_unstable();
+
+        assert_eq!(vec![(0, 0), (0, 1)], out);
+    }
+}
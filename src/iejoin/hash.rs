@@ -0,0 +1,123 @@
+//! Hash join for conjunctions of two equality predicates.
+//!
+//! [`CmpOp`](super::CmpOp) has no equality operator, so an
+//! [`InequalityJoin`](super::InequalityJoin) never falls back to this join;
+//! build it directly.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::iter::{Enumerate, Zip};
+use std::ops::Range;
+use std::vec;
+
+/// Keys for `left = right`, the equality counterpart of
+/// [`JoinPredicate`](super::JoinPredicate).
+#[derive(Debug, Clone)]
+pub struct EquiPredicate<T> {
+    left: Vec<T>,
+    right: Vec<T>,
+}
+
+impl<T> EquiPredicate<T>
+where
+    T: Eq + Hash,
+{
+    pub fn new(left: Vec<T>, right: Vec<T>) -> Self {
+        EquiPredicate { left, right }
+    }
+}
+
+/// Join on `left1 = right1 AND left2 = right2`, yielding `(left_idx,
+/// right_idx)` pairs.
+///
+/// The right side is hashed on both keys and probed with every left row, so
+/// pairs are yielded in left input order, then right input order.
+#[derive(Debug)]
+pub struct HashEquiJoin<T1, T2> {
+    /// Range of each key's rows in `rights`.
+    table: HashMap<(T1, T2), Range<usize>>,
+    /// Right input positions, grouped by key.
+    rights: Vec<usize>,
+    left: Enumerate<Zip<vec::IntoIter<T1>, vec::IntoIter<T2>>>,
+    /// Left row being probed and the range of its remaining right matches.
+    current: Option<(usize, Range<usize>)>,
+}
+
+impl<T1, T2> HashEquiJoin<T1, T2>
+where
+    T1: Eq + Hash,
+    T2: Eq + Hash,
+{
+    /// Create a join matching both predicates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys of a side have different lengths.
+    pub fn new(join1: EquiPredicate<T1>, join2: EquiPredicate<T2>) -> Self {
+        assert_eq!(
+            join1.left.len(),
+            join2.left.len(),
+            "left key lengths differ"
+        );
+        assert_eq!(
+            join1.right.len(),
+            join2.right.len(),
+            "right key lengths differ"
+        );
+
+        let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+        for (idx, key) in join1.right.into_iter().zip(join2.right).enumerate() {
+            groups.entry(key).or_default().push(idx);
+        }
+        let mut rights = Vec::with_capacity(groups.values().map(Vec::len).sum());
+        let table = groups
+            .into_iter()
+            .map(|(key, idxs)| {
+                let start = rights.len();
+                rights.extend(idxs);
+                (key, start..rights.len())
+            })
+            .collect();
+
+        HashEquiJoin {
+            table,
+            rights,
+            left: join1.left.into_iter().zip(join2.left).enumerate(),
+            current: None,
+        }
+    }
+}
+
+impl<T1, T2> Iterator for HashEquiJoin<T1, T2>
+where
+    T1: Eq + Hash,
+    T2: Eq + Hash,
+{
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((left_idx, range)) = self.current.as_mut() {
+                if let Some(pos) = range.next() {
+                    return Some((*left_idx, self.rights[pos]));
+                }
+            }
+            let (left_idx, key) = self.left.next()?;
+            self.current = self.table.get(&key).map(|range| (left_idx, range.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_equi_join() {
+        let join = HashEquiJoin::new(
+            EquiPredicate::new(vec![1, 2, 1, 3], vec![1, 1, 3, 2, 1]),
+            EquiPredicate::new(vec!["a", "b", "b", "c"], vec!["a", "b", "c", "b", "a"]),
+        );
+        let out: Vec<_> = join.collect();
+        assert_eq!(vec![(0, 0), (0, 4), (1, 3), (2, 1), (3, 2)], out);
+    }
+}
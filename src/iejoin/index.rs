@@ -0,0 +1,289 @@
+//! Sorted right side of a join that can be reused across joins.
+use super::{
+    check_union_len, merge_presorted, CmpOp, InequalityJoin, KeyOrder, L1Union, L2Union, Side,
+};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+/// Right rows presorted by both predicates.
+///
+/// Joining fresh left rows against an index only sorts the left side, which is
+/// then merged with the already sorted right side to build the unions.
+///
+/// With the `serde` feature, an index can be serialized and loaded again
+/// without sorting. Deserializing checks that both sides are still sorted.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RightIndex<T1, T2> {
+    order1: KeyOrder,
+    order2: KeyOrder,
+    /// Right keys with their input positions, sorted by the first predicate.
+    right1: Vec<(T1, usize)>,
+    /// Right keys with their input positions, sorted by the second predicate.
+    right2: Vec<(T2, usize)>,
+}
+
+impl<T1, T2> RightIndex<T1, T2>
+where
+    T1: Ord,
+    T2: Ord,
+{
+    /// Sort the right rows of the predicates `left1 op1 right1` and
+    /// `left2 op2 right2`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `right1` and `right2` have different lengths.
+    pub fn new(op1: CmpOp, right1: Vec<T1>, op2: CmpOp, right2: Vec<T2>) -> Self {
+        assert_eq!(
+            right1.len(),
+            right2.len(),
+            "right length mismatch between predicates"
+        );
+
+        let mut right1: Vec<_> = right1
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (v, i))
+            .collect();
+        let mut right2: Vec<_> = right2
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (v, i))
+            .collect();
+        let order1 = KeyOrder::new(op1);
+        let order2 = KeyOrder::new(op2);
+        right1.sort_unstable_by(|a, b| compare_right(&order1, a, b));
+        right2.sort_unstable_by(|a, b| compare_right(&order2, a, b));
+
+        RightIndex {
+            order1,
+            order2,
+            right1,
+            right2,
+        }
+    }
+
+    /// Number of right rows in the index.
+    pub fn len(&self) -> usize {
+        self.right1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.right1.is_empty()
+    }
+}
+
+/// Order of right keys in an index. Every entry is a right entry, so ties only
+/// break on input position.
+fn compare_right<T: Ord>(
+    order: &KeyOrder,
+    (v1, i1): &(T, usize),
+    (v2, i2): &(T, usize),
+) -> Ordering {
+    order
+        .compare((v1, &Side::Right), (v2, &Side::Right))
+        .then(i1.cmp(i2))
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Join left rows against a presorted right side.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `left1` and `left2` have different lengths.
+    pub fn with_right_index(left1: Vec<T1>, left2: Vec<T2>, index: RightIndex<T1, T2>) -> Self {
+        assert_eq!(
+            left1.len(),
+            left2.len(),
+            "left length mismatch between predicates"
+        );
+        check_union_len(left1.len(), index.len()).unwrap_or_else(|e| panic!("{}", e));
+
+        let RightIndex {
+            order1,
+            order2,
+            right1,
+            right2,
+        } = index;
+        let left_len = left1.len();
+
+        let mut left = L1Union::union(left1, Vec::new());
+        left.sort_unstable_by(|a, b| L1Union::compare(&order1, a, b));
+        let right = right1
+            .into_iter()
+            .map(|(v, idx)| (v, Side::Right, idx))
+            .collect();
+        let values = merge_presorted(left, right, false, |(v1, s1, _), (v2, s2, _)| {
+            order1.compare((v1, s1), (v2, s2))
+        });
+        let (perms, l1) = L1Union::from_sorted(values, &order1);
+
+        let mut left = L2Union::union(left2, Vec::new());
+        left.sort_unstable_by(|a, b| L2Union::compare(&order2, left_len, a, b));
+        let right = right2
+            .into_iter()
+            .map(|(v, idx)| (v, left_len + idx))
+            .collect();
+        let values = merge_presorted(left, right, false, |(v1, i1), (v2, i2)| {
+            order2.compare(
+                (v1, &L2Union::<T2>::side(*i1, left_len)),
+                (v2, &L2Union::<T2>::side(*i2, left_len)),
+            )
+        });
+        let l2 = L2Union::from_sorted(values, &order2, perms);
+
+        Self::from_unions(l1, l2)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::{compare_right, RightIndex};
+    use crate::iejoin::KeyOrder;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
+
+    /// Fields of a [`RightIndex`], before they're checked.
+    #[derive(Deserialize)]
+    #[serde(rename = "RightIndex")]
+    struct Fields<T1, T2> {
+        order1: KeyOrder,
+        order2: KeyOrder,
+        right1: Vec<(T1, usize)>,
+        right2: Vec<(T2, usize)>,
+    }
+
+    /// Fails if the sides have different numbers of rows, or if either side's
+    /// input positions aren't a permutation of its rows or its keys aren't
+    /// sorted by its predicate.
+    impl<'de, T1, T2> Deserialize<'de> for RightIndex<T1, T2>
+    where
+        T1: Ord + Deserialize<'de>,
+        T2: Ord + Deserialize<'de>,
+    {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let fields = Fields::<T1, T2>::deserialize(deserializer)?;
+            if fields.right1.len() != fields.right2.len() {
+                return Err(D::Error::custom("right length mismatch between predicates"));
+            }
+            check_sorted(&fields.order1, &fields.right1).map_err(D::Error::custom)?;
+            check_sorted(&fields.order2, &fields.right2).map_err(D::Error::custom)?;
+
+            Ok(RightIndex {
+                order1: fields.order1,
+                order2: fields.order2,
+                right1: fields.right1,
+                right2: fields.right2,
+            })
+        }
+    }
+
+    fn check_sorted<T: Ord>(order: &KeyOrder, entries: &[(T, usize)]) -> Result<(), &'static str> {
+        let mut seen = vec![false; entries.len()];
+        for &(_, idx) in entries {
+            match seen.get_mut(idx) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return Err("input positions aren't a permutation"),
+            }
+        }
+        let sorted = entries
+            .windows(2)
+            .all(|w| compare_right(order, &w[0], &w[1]).is_lt());
+        if !sorted {
+            return Err("keys aren't sorted");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::JoinPredicate;
+
+    fn expected(
+        left1: &[i64],
+        left2: &[i64],
+        right1: &[i64],
+        right2: &[i64],
+    ) -> Vec<(usize, usize)> {
+        let join1 = JoinPredicate::new(CmpOp::LtEq, left1.to_vec(), right1.to_vec());
+        let join2 = JoinPredicate::new(CmpOp::Gt, left2.to_vec(), right2.to_vec());
+        let mut out: Vec<_> = InequalityJoin::new(join1, join2).into_indices().collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn join_with_index() {
+        let (right1, right2) = (vec![5, 3, 8, 3], vec![2, 7, 4, 1]);
+        let (left1, left2) = (vec![3, 6, 1, 9], vec![5, 8, 2, 9]);
+
+        let index = RightIndex::new(CmpOp::LtEq, right1.clone(), CmpOp::Gt, right2.clone());
+        let mut out: Vec<_> = InequalityJoin::with_right_index(left1.clone(), left2.clone(), index)
+            .into_indices()
+            .collect();
+        out.sort();
+
+        assert!(!out.is_empty());
+        assert_eq!(expected(&left1, &left2, &right1, &right2), out);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trip() {
+        let (right1, right2) = (vec![5, 3, 8, 3], vec![2, 7, 4, 1]);
+        let index = RightIndex::new(CmpOp::LtEq, right1.clone(), CmpOp::Gt, right2.clone());
+        let json = serde_json::to_string(&index).unwrap();
+
+        for (left1, left2) in [
+            (vec![3, 6, 1, 9], vec![5, 8, 2, 9]),
+            (vec![4, 0], vec![3, 1]),
+        ] {
+            let index: RightIndex<i64, i64> = serde_json::from_str(&json).unwrap();
+            assert_eq!(4, index.len());
+
+            let mut out: Vec<_> =
+                InequalityJoin::with_right_index(left1.clone(), left2.clone(), index)
+                    .into_indices()
+                    .collect();
+            out.sort();
+            assert_eq!(expected(&left1, &left2, &right1, &right2), out);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_corrupt() {
+        use serde_json::{json, Value};
+
+        let index = RightIndex::new(CmpOp::Lt, vec![5i64, 3, 8], CmpOp::Gt, vec![2i64, 7, 4]);
+        let value = serde_json::to_value(&index).unwrap();
+        // right1 is sorted ascending: [[3, 1], [5, 0], [8, 2]].
+        assert_eq!(json!([[3, 1], [5, 0], [8, 2]]), value["right1"]);
+
+        let load = |value: Value| serde_json::from_value::<RightIndex<i64, i64>>(value);
+        assert!(load(value.clone()).is_ok());
+
+        let corrupt = |f: fn(&mut Value)| {
+            let mut value = value.clone();
+            f(&mut value);
+            load(value).unwrap_err().to_string()
+        };
+        let err = corrupt(|v| v["right1"][1][1] = json!(1));
+        assert!(err.contains("permutation"), "{}", err);
+        let err = corrupt(|v| v["right1"][0][1] = json!(9));
+        assert!(err.contains("permutation"), "{}", err);
+        let err = corrupt(|v| v["right1"][0][0] = json!(100));
+        assert!(err.contains("sorted"), "{}", err);
+        let err = corrupt(|v| {
+            v["right2"].as_array_mut().unwrap().pop();
+        });
+        assert!(err.contains("length mismatch"), "{}", err);
+    }
+}
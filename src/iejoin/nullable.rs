@@ -0,0 +1,179 @@
+//! Joins over keys that may be null.
+use super::{validate, InequalityJoin, JoinPredicate, JoinScratch, L1Union, L2Union, Side};
+use std::fmt::Debug;
+
+/// Where rows with a null key are placed in the first predicate's sort, see
+/// [`InequalityJoin::new_nullable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullOrder {
+    First,
+    Last,
+}
+
+impl<T1, T2> InequalityJoin<Option<T1>, Option<T2>>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Create a join where `None` keys are null. A row with a null key in
+    /// either predicate never matches.
+    ///
+    /// Null rows are kept in the unions, placed before or after the other
+    /// rows of the first predicate per `nulls`. This shows in
+    /// [`InequalityJoin::left_permutation`] and
+    /// [`InequalityJoin::right_permutation`], but doesn't change the matches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
+    pub fn new_nullable(
+        join1: JoinPredicate<Option<T1>>,
+        join2: JoinPredicate<Option<T2>>,
+        nulls: NullOrder,
+    ) -> Self {
+        validate(&join1, &join2).unwrap_or_else(|e| panic!("{}", e));
+
+        let left_len = join1.left.len();
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        let left_null: Vec<_> = join1
+            .left
+            .iter()
+            .zip(&join2.left)
+            .map(|(k1, k2)| k1.is_none() || k2.is_none())
+            .collect();
+        let right_null: Vec<_> = join1
+            .right
+            .iter()
+            .zip(&join2.right)
+            .map(|(k1, k2)| k1.is_none() || k2.is_none())
+            .collect();
+        let is_null = |side: &Side, idx: usize| match side {
+            Side::Left => left_null[idx],
+            Side::Right => right_null[idx],
+        };
+
+        // A pair only matches if the left comes first in both unions. Null
+        // rights precede null lefts in L1, and in L2 null rights are at the
+        // very start and null lefts at the very end, so neither can match.
+        let (mut nulls1, mut values1): (Vec<_>, Vec<_>) = L1Union::union(join1.left, join1.right)
+            .into_iter()
+            .partition(|(_, side, idx)| is_null(side, *idx));
+        nulls1.sort_by_key(|(_, side, _)| side.is_left());
+        values1.sort_unstable_by(|a, b| L1Union::compare(&order1, a, b));
+        let values1 = match nulls {
+            NullOrder::First => nulls1.into_iter().chain(values1).collect(),
+            NullOrder::Last => values1.into_iter().chain(nulls1).collect(),
+        };
+        let (perms, l1) = L1Union::from_sorted(values1, &order1);
+
+        let side2 = |idx: usize| {
+            if idx < left_len {
+                (Side::Left, idx)
+            } else {
+                (Side::Right, idx - left_len)
+            }
+        };
+        let (nulls2, mut values2): (Vec<_>, Vec<_>) = L2Union::union(join2.left, join2.right)
+            .into_iter()
+            .partition(|(_, idx)| {
+                let (side, idx) = side2(*idx);
+                is_null(&side, idx)
+            });
+        let (null_lefts, null_rights): (Vec<_>, Vec<_>) =
+            nulls2.into_iter().partition(|(_, idx)| *idx < left_len);
+        values2.sort_unstable_by(|a, b| L2Union::compare(&order2, left_len, a, b));
+        let values2 = null_rights
+            .into_iter()
+            .chain(values2)
+            .chain(null_lefts)
+            .collect();
+        let l2 = L2Union::from_sorted(values2, &order2, perms);
+
+        Self::from_unions_with_scratch(l1, l2, JoinScratch::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::tests::brute_force;
+    use crate::iejoin::CmpOp;
+
+    /// Brute-force matches of the predicates, skipping rows with a null key.
+    fn non_null_matches<T1: Ord, T2: Ord>(
+        join1: &JoinPredicate<Option<T1>>,
+        join2: &JoinPredicate<Option<T2>>,
+    ) -> Vec<(usize, usize)> {
+        brute_force(join1, join2)
+            .into_iter()
+            .filter(|&(l, r)| {
+                join1.left[l].is_some()
+                    && join1.right[r].is_some()
+                    && join2.left[l].is_some()
+                    && join2.right[r].is_some()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn new_nullable() {
+        let left1 = vec![Some(4), None, Some(7), Some(3), Some(3)];
+        let left2 = vec![Some(2), Some(9), Some(4), None, Some(0)];
+        let right1 = vec![Some(5), Some(2), None, Some(3)];
+        let right2 = vec![Some(1), Some(4), Some(6), Some(0)];
+
+        let join = |nulls| {
+            InequalityJoin::new_nullable(
+                JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+                JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone()),
+                nulls,
+            )
+        };
+
+        let expected = non_null_matches(
+            &JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+            &JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone()),
+        );
+        assert!(!expected.is_empty());
+
+        for nulls in [NullOrder::First, NullOrder::Last] {
+            let mut out: Vec<_> = join(nulls).into_indices().collect();
+            out.sort_unstable();
+            assert_eq!(expected, out, "{:?}", nulls);
+        }
+
+        // Null rows sort first or last.
+        assert_eq!(
+            vec![1, 3, 4, 0, 2],
+            join(NullOrder::First).left_permutation()
+        );
+        assert_eq!(
+            vec![4, 0, 2, 1, 3],
+            join(NullOrder::Last).left_permutation()
+        );
+        assert_eq!(vec![2, 1, 3, 0], join(NullOrder::First).right_permutation());
+        assert_eq!(vec![1, 3, 0, 2], join(NullOrder::Last).right_permutation());
+    }
+
+    #[test]
+    fn nullable_ops() {
+        let left1 = vec![Some(4), None, Some(7), Some(3), Some(3), Some(5)];
+        let left2 = vec![Some(2), Some(9), None, Some(4), Some(0), Some(4)];
+        let right1 = vec![Some(5), Some(2), None, Some(3), Some(8)];
+        let right2 = vec![Some(1), Some(4), Some(6), None, Some(4)];
+        let ops = [CmpOp::Lt, CmpOp::LtEq, CmpOp::Gt, CmpOp::GtEq];
+
+        for nulls in [NullOrder::First, NullOrder::Last] {
+            for op1 in ops {
+                for op2 in ops {
+                    let join1 = JoinPredicate::new(op1, left1.clone(), right1.clone());
+                    let join2 = JoinPredicate::new(op2, left2.clone(), right2.clone());
+                    let expected = non_null_matches(&join1, &join2);
+                    let out = InequalityJoin::new_nullable(join1, join2, nulls).collect_sorted();
+                    assert_eq!(expected, out, "{:?} {:?} {:?}", nulls, op1, op2);
+                }
+            }
+        }
+    }
+}
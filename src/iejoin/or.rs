@@ -0,0 +1,87 @@
+//! Disjunctions of inequality joins.
+use std::collections::HashSet;
+
+/// Union of two joins over the same left and right inputs, for predicates of
+/// the form `(p1 AND p2) OR (p3 AND p4)`.
+///
+/// Both joins are given as `(left_idx, right_idx)` matches, e.g. from
+/// [`InequalityJoin::into_indices`](super::InequalityJoin::into_indices).
+/// Matches of the first join are yielded before those of the second, and a
+/// pair matched by both is yielded once.
+#[derive(Debug)]
+pub struct JoinUnion<A, B> {
+    a: A,
+    b: B,
+    seen: HashSet<(usize, usize)>,
+}
+
+impl<A, B> JoinUnion<A, B>
+where
+    A: Iterator<Item = (usize, usize)>,
+    B: Iterator<Item = (usize, usize)>,
+{
+    pub fn new(a: A, b: B) -> Self {
+        JoinUnion {
+            a,
+            b,
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<A, B> Iterator for JoinUnion<A, B>
+where
+    A: Iterator<Item = (usize, usize)>,
+    B: Iterator<Item = (usize, usize)>,
+{
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let pair = match self.a.next() {
+                Some(pair) => pair,
+                None => self.b.next()?,
+            };
+            if self.seen.insert(pair) {
+                return Some(pair);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+
+    #[test]
+    fn join_union() {
+        let (lx, ly) = (vec![1, 4, 6, 3], vec![5, 2, 8, 1]);
+        let (rx, ry, rz) = (vec![3, 5, 7], vec![6, 3, 9], vec![0, 5, 2]);
+
+        // (lx < rx AND ly < ry) OR (lx > rz AND ly < ry)
+        let a = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, lx.clone(), rx.clone()),
+            JoinPredicate::new(CmpOp::Lt, ly.clone(), ry.clone()),
+        );
+        let b = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Gt, lx.clone(), rz.clone()),
+            JoinPredicate::new(CmpOp::Lt, ly.clone(), ry.clone()),
+        );
+        let a: Vec<_> = a.into_indices().collect();
+        let b: Vec<_> = b.into_indices().collect();
+        assert!(a.iter().any(|pair| b.contains(pair)));
+
+        let out: Vec<_> = JoinUnion::new(a.into_iter(), b.into_iter()).collect();
+        let mut distinct = out.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(out.len(), distinct.len());
+
+        let expected: Vec<_> = (0..lx.len())
+            .flat_map(|l| (0..rx.len()).map(move |r| (l, r)))
+            .filter(|&(l, r)| (lx[l] < rx[r] || lx[l] > rz[r]) && ly[l] < ry[r])
+            .collect();
+        assert_eq!(expected, distinct);
+    }
+}
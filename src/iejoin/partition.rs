@@ -0,0 +1,243 @@
+//! Inequality joins within equi-join groups.
+use super::{CmpOp, InequalityJoin, JoinPredicate};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// Rows of one side of a group, with their input positions.
+struct Rows<T1, T2> {
+    idx: Vec<usize>,
+    key1: Vec<T1>,
+    key2: Vec<T2>,
+}
+
+impl<T1, T2> Rows<T1, T2> {
+    fn new() -> Self {
+        Rows {
+            idx: Vec::new(),
+            key1: Vec::new(),
+            key2: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, idx: usize, key1: T1, key2: T2) {
+        self.idx.push(idx);
+        self.key1.push(key1);
+        self.key2.push(key2);
+    }
+}
+
+/// Keys of one group's rows, to build the group's predicates from, see
+/// [`InequalityJoin::partitioned_with_predicates`].
+#[derive(Debug)]
+pub struct GroupKeys<T1, T2> {
+    pub left1: Vec<T1>,
+    pub right1: Vec<T1>,
+    pub left2: Vec<T2>,
+    pub right2: Vec<T2>,
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Join `(group, key1, key2)` rows, matching only rows of equal groups.
+    ///
+    /// Each group is joined on its own operators, `predicates(group)` giving
+    /// `(op1, op2)`. Matches are yielded as `(left_idx, right_idx)` input
+    /// positions, one group at a time in ascending group order.
+    pub fn partitioned<G, F>(
+        left: Vec<(G, T1, T2)>,
+        right: Vec<(G, T1, T2)>,
+        predicates: F,
+    ) -> impl Iterator<Item = (usize, usize)>
+    where
+        G: Ord,
+        F: Fn(&G) -> (CmpOp, CmpOp),
+    {
+        Self::partitioned_with_predicates(left, right, move |group, keys| {
+            group_predicates(predicates(group), keys)
+        })
+    }
+
+    /// Like [`InequalityJoin::partitioned`], with `predicates(group, keys)`
+    /// building each group's predicates from its keys, e.g. to offset them
+    /// per group with [`JoinPredicate::with_left_transform`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a group's predicates are invalid, see
+    /// [`InequalityJoin::try_new`].
+    pub fn partitioned_with_predicates<G, F>(
+        left: Vec<(G, T1, T2)>,
+        right: Vec<(G, T1, T2)>,
+        predicates: F,
+    ) -> impl Iterator<Item = (usize, usize)>
+    where
+        G: Ord,
+        F: Fn(&G, GroupKeys<T1, T2>) -> (JoinPredicate<T1>, JoinPredicate<T2>),
+    {
+        group_rows(left, right)
+            .into_iter()
+            .flat_map(move |(group, (left, right))| {
+                let (left_idx, right_idx, keys) = group_keys(left, right);
+                let (join1, join2) = predicates(&group, keys);
+                join_group(left_idx, right_idx, join1, join2)
+            })
+    }
+
+    /// Like [`InequalityJoin::partitioned`], yielding each match along with
+    /// its group as `(group, left_idx, right_idx)`.
+    pub fn partitioned_with_key<G, F>(
+        left: Vec<(G, T1, T2)>,
+        right: Vec<(G, T1, T2)>,
+        predicates: F,
+    ) -> impl Iterator<Item = (G, usize, usize)>
+    where
+        G: Ord + Clone,
+        F: Fn(&G) -> (CmpOp, CmpOp),
+    {
+        group_rows(left, right)
+            .into_iter()
+            .flat_map(move |(group, (left, right))| {
+                let (left_idx, right_idx, keys) = group_keys(left, right);
+                let (join1, join2) = group_predicates(predicates(&group), keys);
+                join_group(left_idx, right_idx, join1, join2)
+                    .map(move |(l, r)| (group.clone(), l, r))
+            })
+    }
+}
+
+/// Left and right rows of each group.
+type Groups<G, T1, T2> = BTreeMap<G, (Rows<T1, T2>, Rows<T1, T2>)>;
+
+/// Split rows by group. Groups without left rows are dropped.
+fn group_rows<G, T1, T2>(left: Vec<(G, T1, T2)>, right: Vec<(G, T1, T2)>) -> Groups<G, T1, T2>
+where
+    G: Ord,
+{
+    let mut groups = BTreeMap::new();
+    for (idx, (group, key1, key2)) in left.into_iter().enumerate() {
+        let (rows, _) = groups
+            .entry(group)
+            .or_insert_with(|| (Rows::new(), Rows::new()));
+        rows.push(idx, key1, key2);
+    }
+    for (idx, (group, key1, key2)) in right.into_iter().enumerate() {
+        // Right rows without a left group can't match.
+        if let Some((_, rows)) = groups.get_mut(&group) {
+            rows.push(idx, key1, key2);
+        }
+    }
+    groups
+}
+
+/// Split a group's rows into their input positions and keys.
+fn group_keys<T1, T2>(
+    left: Rows<T1, T2>,
+    right: Rows<T1, T2>,
+) -> (Vec<usize>, Vec<usize>, GroupKeys<T1, T2>) {
+    let keys = GroupKeys {
+        left1: left.key1,
+        right1: right.key1,
+        left2: left.key2,
+        right2: right.key2,
+    };
+    (left.idx, right.idx, keys)
+}
+
+fn group_predicates<T1, T2>(
+    (op1, op2): (CmpOp, CmpOp),
+    keys: GroupKeys<T1, T2>,
+) -> (JoinPredicate<T1>, JoinPredicate<T2>)
+where
+    T1: Ord,
+    T2: Ord,
+{
+    (
+        JoinPredicate::new(op1, keys.left1, keys.right1),
+        JoinPredicate::new(op2, keys.left2, keys.right2),
+    )
+}
+
+/// Join the rows of one group, yielding matches as input positions.
+fn join_group<T1, T2>(
+    left_idx: Vec<usize>,
+    right_idx: Vec<usize>,
+    join1: JoinPredicate<T1>,
+    join2: JoinPredicate<T2>,
+) -> impl Iterator<Item = (usize, usize)>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    InequalityJoin::new(join1, join2)
+        .into_indices()
+        .map(move |(l, r)| (left_idx[l], right_idx[r]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_group_predicates() {
+        let left = vec![("a", 1, 1), ("b", 1, 1), ("a", 2, 2), ("b", 2, 2)];
+        let right = vec![("b", 2, 2), ("a", 2, 2), ("c", 9, 9), ("a", 3, 3)];
+
+        let mut out: Vec<_> = InequalityJoin::partitioned(left, right, |group| match *group {
+            "a" => (CmpOp::Lt, CmpOp::Lt),
+            _ => (CmpOp::LtEq, CmpOp::LtEq),
+        })
+        .collect();
+        out.sort();
+
+        // Group "a" only matches strictly greater rights, group "b" matches
+        // equal keys too.
+        assert_eq!(vec![(0, 1), (0, 3), (1, 0), (2, 3), (3, 0)], out);
+    }
+
+    #[test]
+    fn per_group_offsets() {
+        let left = vec![("a", 1, 1), ("b", 1, 1), ("a", 2, 2), ("b", 2, 2)];
+        let right = vec![("b", 2, 2), ("a", 2, 2), ("c", 9, 9), ("a", 3, 3)];
+
+        let mut out: Vec<_> =
+            InequalityJoin::partitioned_with_predicates(left, right, |group, keys| {
+                // Group "a" matches `left1 + 1 < right1`, group "b"
+                // `left1 <= right1`.
+                let join1 = match *group {
+                    "a" => JoinPredicate::new(CmpOp::Lt, keys.left1, keys.right1)
+                        .with_left_transform(|k| k + 1),
+                    _ => JoinPredicate::new(CmpOp::LtEq, keys.left1, keys.right1),
+                };
+                let join2 = JoinPredicate::new(CmpOp::LtEq, keys.left2, keys.right2);
+                (join1, join2)
+            })
+            .collect();
+        out.sort();
+
+        // Left 2 in group "a" no longer matches right 3 with the offset.
+        assert_eq!(vec![(0, 3), (1, 0), (3, 0)], out);
+    }
+
+    #[test]
+    fn partitioned_with_key() {
+        let left = vec![("a", 1, 5), ("b", 1, 5), ("a", 2, 0)];
+        let right = vec![("b", 2, 2), ("a", 2, 2), ("c", 9, 0), ("a", 3, 1)];
+
+        let mut out: Vec<_> =
+            InequalityJoin::partitioned_with_key(left.clone(), right.clone(), |_| {
+                (CmpOp::Lt, CmpOp::Gt)
+            })
+            .collect();
+        out.sort();
+        assert_eq!(vec![("a", 0, 1), ("a", 0, 3), ("b", 1, 0)], out);
+
+        // Every match is between rows of its group.
+        for (group, l, r) in out {
+            assert_eq!(group, left[l].0);
+            assert_eq!(group, right[r].0);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Reusing the sorted left side of the first predicate across joins.
+use super::{
+    merge_presorted, CmpOp, InequalityJoin, JoinPredicate, JoinScratch, KeyOrder, L1Union, L2Union,
+    Side,
+};
+use std::fmt::Debug;
+
+/// Left keys of the first predicate, sorted once for joining against any
+/// number of right inputs.
+///
+/// Each join only sorts its right keys and merges them with the prepared left
+/// keys to build L1.
+#[derive(Debug, Clone)]
+pub struct PreparedLeft<T> {
+    values: Vec<(T, Side, usize)>,
+    order: KeyOrder,
+}
+
+impl<T> PreparedLeft<T>
+where
+    T: Ord + Clone + Debug,
+{
+    pub fn new(op: CmpOp, left: Vec<T>) -> Self {
+        let order = KeyOrder::new(op);
+        let mut values: Vec<_> = left
+            .into_iter()
+            .enumerate()
+            .map(|(idx, v)| (v, Side::Left, idx))
+            .collect();
+        values.sort_unstable_by(|a, b| L1Union::compare(&order, a, b));
+        PreparedLeft { values, order }
+    }
+
+    /// Number of left rows.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Join the prepared left keys against `right` for the first predicate,
+    /// and `join2` for the second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `join2` doesn't have a left row for every prepared left key
+    /// and a right row for every key in `right`.
+    pub fn join<T2>(&self, right: Vec<T>, join2: JoinPredicate<T2>) -> InequalityJoin<T, T2>
+    where
+        T2: Ord + Debug,
+    {
+        assert_eq!(self.len(), join2.left.len(), "left lengths differ");
+        assert_eq!(right.len(), join2.right.len(), "right lengths differ");
+
+        let order = self.order;
+        let mut right: Vec<_> = right
+            .into_iter()
+            .enumerate()
+            .map(|(idx, v)| (v, Side::Right, idx))
+            .collect();
+        right.sort_unstable_by(|a, b| L1Union::compare(&order, a, b));
+        let values = merge_presorted(
+            self.values.clone(),
+            right,
+            false,
+            |(v1, s1, _), (v2, s2, _)| order.compare((v1, s1), (v2, s2)),
+        );
+        let (perms, l1) = L1Union::from_sorted(values, &order);
+        let l2 = L2Union::from_predicate(join2, perms);
+
+        InequalityJoin::from_unions_with_scratch(l1, l2, JoinScratch::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_prepared_left() {
+        let (left1, left2) = (vec![4, 1, 7, 3], vec![2, 9, 4, 4]);
+        let prepared = PreparedLeft::new(CmpOp::Lt, left1.clone());
+
+        let rights = [
+            (vec![5, 2, 8, 3], vec![1, 4, 6, 0]),
+            (vec![3, 9, 1], vec![5, 3, 3]),
+        ];
+        for (right1, right2) in rights.iter() {
+            let mut expected: Vec<_> = InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+                JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone()),
+            )
+            .into_indices()
+            .collect();
+            expected.sort_unstable();
+
+            let join2 = JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone());
+            let mut out: Vec<_> = prepared
+                .join(right1.clone(), join2)
+                .into_indices()
+                .collect();
+            out.sort_unstable();
+            assert!(!out.is_empty());
+            assert_eq!(expected, out);
+        }
+    }
+}
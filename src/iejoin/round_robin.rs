@@ -0,0 +1,64 @@
+//! Interleaving the matches of several joins.
+use super::InequalityJoin;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+/// Yields one match from each join in turn, skipping joins once they're
+/// exhausted.
+#[derive(Debug)]
+pub struct RoundRobinJoins<T1, T2> {
+    joins: VecDeque<InequalityJoin<T1, T2>>,
+}
+
+impl<T1, T2> RoundRobinJoins<T1, T2> {
+    pub fn new(joins: Vec<InequalityJoin<T1, T2>>) -> Self {
+        RoundRobinJoins {
+            joins: joins.into(),
+        }
+    }
+}
+
+impl<T1, T2> Iterator for RoundRobinJoins<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    type Item = (T1, T2);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(mut join) = self.joins.pop_front() {
+            if let Some(pair) = join.next() {
+                self.joins.push_back(join);
+                return Some(pair);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::{CmpOp, JoinPredicate};
+
+    #[test]
+    fn round_robin() {
+        let join = |left: Vec<i32>, right: Vec<i32>| {
+            InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, left.clone(), right.clone()),
+                JoinPredicate::new(CmpOp::Lt, left, right),
+            )
+        };
+        let a = || join(vec![1, 2], vec![3]);
+        let b = || join(vec![10, 11, 12, 13], vec![20]);
+
+        let a_out: Vec<_> = a().collect();
+        let b_out: Vec<_> = b().collect();
+        assert_eq!(2, a_out.len());
+        assert_eq!(4, b_out.len());
+
+        let out: Vec<_> = RoundRobinJoins::new(vec![a(), b()]).collect();
+        let expected = vec![a_out[0], b_out[0], a_out[1], b_out[1], b_out[2], b_out[3]];
+        assert_eq!(expected, out);
+    }
+}
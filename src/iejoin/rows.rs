@@ -0,0 +1,91 @@
+//! Joins over rows with keys computed by closures.
+use super::{CmpOp, InequalityJoin, InequalityJoinIndices, JoinPredicate};
+use std::fmt::Debug;
+
+/// Join of two slices of rows, yielding references to matching rows. See
+/// [`InequalityJoin::on`].
+#[derive(Debug)]
+pub struct RowJoin<'a, T1, T2, R> {
+    join: InequalityJoinIndices<T1, T2>,
+    left: &'a [R],
+    right: &'a [R],
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Join rows where `key1(left) op1 key1(right)` and
+    /// `key2(left) op2 key2(right)`, yielding the matching row pairs.
+    ///
+    /// Keys are extracted once per row before building the join.
+    pub fn on<'a, R, K1, K2>(
+        left: &'a [R],
+        right: &'a [R],
+        key1: K1,
+        key2: K2,
+        op1: CmpOp,
+        op2: CmpOp,
+    ) -> RowJoin<'a, T1, T2, R>
+    where
+        K1: Fn(&R) -> T1,
+        K2: Fn(&R) -> T2,
+    {
+        let join = InequalityJoin::new(
+            JoinPredicate::new(
+                op1,
+                left.iter().map(&key1).collect(),
+                right.iter().map(&key1).collect(),
+            ),
+            JoinPredicate::new(
+                op2,
+                left.iter().map(&key2).collect(),
+                right.iter().map(&key2).collect(),
+            ),
+        );
+
+        RowJoin {
+            join: join.into_indices(),
+            left,
+            right,
+        }
+    }
+}
+
+impl<'a, T1, T2, R> Iterator for RowJoin<'a, T1, T2, R> {
+    type Item = (&'a R, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (left_idx, right_idx) = self.join.next()?;
+        Some((&self.left[left_idx], &self.right[right_idx]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Trip {
+        id: u32,
+        start: u32,
+        cost: u32,
+    }
+
+    #[test]
+    fn on_extracted_keys() {
+        let trip = |id, start, cost| Trip { id, start, cost };
+        let left = vec![trip(0, 5, 10), trip(1, 2, 30), trip(2, 8, 5)];
+        let right = vec![trip(10, 6, 20), trip(11, 9, 1), trip(12, 3, 40)];
+
+        // Trips on the right starting later but costing less.
+        let mut out: Vec<_> =
+            InequalityJoin::on(&left, &right, |t| t.start, |t| t.cost, CmpOp::Lt, CmpOp::Gt)
+                .map(|(l, r)| (l.id, r.id))
+                .collect();
+        out.sort_unstable();
+
+        assert_eq!(vec![(0, 11), (1, 10), (1, 11), (2, 11)], out);
+    }
+}
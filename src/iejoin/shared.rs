@@ -0,0 +1,85 @@
+//! Several scans over one set of sorted unions.
+use super::{InequalityJoin, L1BitVec, L1Union, L2Union, ScanRange, UnmatchedLefts};
+use std::sync::Arc;
+
+/// Sorted unions shared by any number of scans, see
+/// [`InequalityJoin::shared`].
+#[derive(Debug)]
+pub struct SharedJoin<T1, T2> {
+    unions: Arc<(L1Union<T1>, L2Union<T2>)>,
+    l2_end: usize,
+    /// Cleared bitvec each scan starts from.
+    bitvec: L1BitVec,
+}
+
+impl<T1, T2> InequalityJoin<T1, T2> {
+    /// Share the sorted unions between scans, e.g. to run the inner and the
+    /// anti join without sorting twice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scan has already started.
+    pub fn shared(self) -> SharedJoin<T1, T2> {
+        assert_eq!(0, self.l2_idx, "can't share once the scan has started");
+        SharedJoin {
+            unions: Arc::new((self.l1, self.l2)),
+            l2_end: self.l2_end,
+            bitvec: self.bitvec,
+        }
+    }
+}
+
+impl<T1, T2> SharedJoin<T1, T2> {
+    /// Matches as `(left_idx, right_idx)` positions in the input vectors, like
+    /// [`InequalityJoin::into_indices`].
+    pub fn inner(&self) -> ScanRange<T1, T2> {
+        ScanRange {
+            unions: self.unions.clone(),
+            l2_idx: 0,
+            l2_end: self.l2_end,
+            bitvec: self.bitvec.clone(),
+        }
+    }
+
+    /// Input positions of left rows without any match, like
+    /// [`InequalityJoin::unmatched_lefts`].
+    pub fn anti(&self) -> UnmatchedLefts<T1, T2> {
+        UnmatchedLefts::new(self.unions.clone())
+    }
+}
+
+impl<T1, T2> Clone for SharedJoin<T1, T2> {
+    fn clone(&self) -> Self {
+        SharedJoin {
+            unions: self.unions.clone(),
+            l2_end: self.l2_end,
+            bitvec: self.bitvec.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iejoin::tests::fixture_join;
+
+    #[test]
+    fn inner_and_anti() {
+        let expected: Vec<_> = fixture_join().into_indices().collect();
+        let shared = fixture_join().shared();
+
+        let mut inner = shared.inner();
+        let first = inner.next();
+        let rest: Vec<_> = inner.clone().collect();
+        assert_eq!(rest, inner.collect::<Vec<_>>());
+        let out: Vec<_> = first.into_iter().chain(rest).collect();
+        assert_eq!(expected, out);
+
+        let mut anti: Vec<_> = shared.anti().collect();
+        anti.sort_unstable();
+        let unmatched: Vec<_> = (0..5)
+            .filter(|l| !expected.iter().any(|(left, _)| left == l))
+            .collect();
+        assert!(!anti.is_empty());
+        assert_eq!(unmatched, anti);
+    }
+}
@@ -0,0 +1,106 @@
+//! Joins over randomly permuted inputs, for checking that results don't
+//! depend on input order.
+use super::{validate, InequalityJoin, JoinPredicate};
+use std::fmt::Debug;
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Create a join after shuffling the left and right rows with a PRNG
+    /// seeded by `seed`.
+    ///
+    /// Rows keep their keys across both predicates, so the join yields the
+    /// same matches as [`InequalityJoin::new`], possibly in a different order
+    /// and with different input positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the predicates are invalid, see [`InequalityJoin::try_new`].
+    pub fn new_shuffled(join1: JoinPredicate<T1>, join2: JoinPredicate<T2>, seed: u64) -> Self {
+        validate(&join1, &join2).unwrap_or_else(|e| panic!("{}", e));
+
+        let mut rng = XorShift::new(seed);
+        let left = rng.permutation(join1.left.len());
+        let right = rng.permutation(join1.right.len());
+
+        let shuffle1 = JoinPredicate {
+            left: permute(join1.left, &left),
+            right: permute(join1.right, &right),
+            // Shuffled rows are no longer presorted.
+            input_order: None,
+            ..join1
+        };
+        let shuffle2 = JoinPredicate {
+            left: permute(join2.left, &left),
+            right: permute(join2.right, &right),
+            input_order: None,
+            ..join2
+        };
+        Self::new(shuffle1, shuffle2)
+    }
+}
+
+/// Reorder `values` so that position `i` holds `values[perm[i]]`.
+fn permute<T>(values: Vec<T>, perm: &[usize]) -> Vec<T> {
+    let mut values: Vec<_> = values.into_iter().map(Some).collect();
+    perm.iter()
+        .map(|&idx| values[idx].take().unwrap())
+        .collect()
+}
+
+/// Minimal xorshift64 generator. Not suitable for anything but testing.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        XorShift(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Fisher-Yates shuffle of `0..len`.
+    fn permutation(&mut self, len: usize) -> Vec<usize> {
+        let mut perm: Vec<_> = (0..len).collect();
+        for i in (1..len).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            perm.swap(i, j);
+        }
+        perm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::CmpOp;
+
+    #[test]
+    fn shuffled_seeds() {
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, vec![4, 1, 7, 3, 3, 0, 5], vec![5, 2, 8, 3, 3]),
+                JoinPredicate::new(CmpOp::GtEq, vec![2, 9, 4, 4, 0, 7, 4], vec![1, 4, 6, 0, 4]),
+            )
+        };
+
+        let (join1, join2) = join();
+        let mut expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        expected.sort();
+        assert!(!expected.is_empty());
+
+        for seed in 0..16 {
+            let (join1, join2) = join();
+            let mut out: Vec<_> = InequalityJoin::new_shuffled(join1, join2, seed).collect();
+            out.sort();
+            assert_eq!(expected, out, "seed: {}", seed);
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Building the unions with a merge sort that spills its runs to disk.
+//!
+//! When a union holds more entries than the configured run length, it's
+//! sorted in runs of at most that many entries. Each sorted run is spilled to
+//! a file, and the runs are then merged back into the union.
+//!
+//! This is not an out-of-core join. The inputs and both unions stay in
+//! memory, so a join still needs about as much memory as with
+//! [`InequalityJoin::new`]. The unsorted union is freed once every run is
+//! spilled, before the merge builds the sorted one, so the two are never
+//! held at once.
+use super::{validate, InequalityJoin, JoinPredicate, KeyOrder, L1Union, L2Union, Side};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Values that can be written to and read back from a spill file.
+pub trait Spill: Sized {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()>;
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_spill_int {
+    ($($t:ty),*) => {
+        $(
+            impl Spill for $t {
+                fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+                    let mut buf = [0; std::mem::size_of::<$t>()];
+                    r.read_exact(&mut buf)?;
+                    Ok(<$t>::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_spill_int!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+/// Spilled as 64 bits so spill files are the same on every target.
+impl Spill for usize {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u64).spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        let v = u64::unspill(r)?;
+        usize::try_from(v).map_err(|_| invalid_data(format!("{} doesn't fit in usize", v)))
+    }
+}
+
+/// Spilled as 64 bits so spill files are the same on every target.
+impl Spill for isize {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        (*self as i64).spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        let v = i64::unspill(r)?;
+        isize::try_from(v).map_err(|_| invalid_data(format!("{} doesn't fit in isize", v)))
+    }
+}
+
+impl Spill for Side {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            Side::Left => 0u8.spill(w),
+            Side::Right => 1u8.spill(w),
+        }
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        match u8::unspill(r)? {
+            0 => Ok(Side::Left),
+            1 => Ok(Side::Right),
+            b => Err(invalid_data(format!("invalid side: {}", b))),
+        }
+    }
+}
+
+impl Spill for bool {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        u8::from(*self).spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        match u8::unspill(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(invalid_data(format!("invalid bool: {}", b))),
+        }
+    }
+}
+
+impl Spill for char {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        u32::from(*self).spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        let v = u32::unspill(r)?;
+        char::from_u32(v).ok_or_else(|| invalid_data(format!("invalid char: {}", v)))
+    }
+}
+
+/// Spilled as its length followed by its elements.
+impl<T: Spill> Spill for Vec<T> {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.len().spill(w)?;
+        self.iter().try_for_each(|v| v.spill(w))
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        let len = usize::unspill(r)?;
+        (0..len).map(|_| T::unspill(r)).collect()
+    }
+}
+
+impl Spill for String {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.len().spill(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        let len = usize::unspill(r)?;
+        let mut buf = vec![0; len];
+        r.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| invalid_data(e.to_string()))
+    }
+}
+
+impl<T: Spill> Spill for Option<T> {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            None => false.spill(w),
+            Some(v) => {
+                true.spill(w)?;
+                v.spill(w)
+            }
+        }
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        if bool::unspill(r)? {
+            T::unspill(r).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Spill, B: Spill> Spill for (A, B) {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.spill(w)?;
+        self.1.spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok((A::unspill(r)?, B::unspill(r)?))
+    }
+}
+
+impl<A: Spill, B: Spill, C: Spill> Spill for (A, B, C) {
+    fn spill<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.0.spill(w)?;
+        self.1.spill(w)?;
+        self.2.spill(w)
+    }
+
+    fn unspill<R: Read>(r: &mut R) -> io::Result<Self> {
+        Ok((A::unspill(r)?, B::unspill(r)?, C::unspill(r)?))
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Settings for [`InequalityJoin::new_spill_sorted`].
+#[derive(Debug, Clone)]
+pub struct SpillSort {
+    run_len: usize,
+    dir: PathBuf,
+}
+
+impl SpillSort {
+    /// Sort unions in runs of at most `run_len` entries, spilled to the
+    /// system's temp dir.
+    pub fn new(run_len: usize) -> Self {
+        SpillSort {
+            run_len,
+            dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Spill runs to `dir` instead.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug + Spill,
+    T2: Ord + Debug + Spill,
+{
+    /// Create a join, sorting each union with spilled runs if it has more
+    /// entries than the run length, see the [module docs](self).
+    pub fn new_spill_sorted(
+        join1: JoinPredicate<T1>,
+        join2: JoinPredicate<T2>,
+        settings: &SpillSort,
+    ) -> io::Result<Self> {
+        validate(&join1, &join2).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let left_len = join1.left.len();
+        let order1 = join1.key_order();
+        let order2 = join2.key_order();
+        let (perms, l1) =
+            L1Union::union_and_sort_spilled(join1.left, join1.right, &order1, settings)?;
+        let l2 = L2Union::union_and_sort_with_permutations_spilled(
+            join2.left,
+            join2.right,
+            &order2,
+            left_len,
+            perms,
+            settings,
+        )?;
+
+        Ok(Self::from_unions(l1, l2))
+    }
+}
+
+impl<T> L1Union<T>
+where
+    T: Ord + Spill,
+{
+    fn union_and_sort_spilled(
+        left: Vec<T>,
+        right: Vec<T>,
+        order: &KeyOrder,
+        settings: &SpillSort,
+    ) -> io::Result<(Vec<usize>, Self)> {
+        if order.presorted.is_some() {
+            // Merging presorted inputs doesn't need a sort to spill.
+            return Ok(Self::union_and_sort(left, right, order));
+        }
+        let values = Self::union(left, right);
+        let values = sort_by(values, settings, |a, b| Self::compare(order, a, b))?;
+        Ok(Self::from_sorted(values, order))
+    }
+}
+
+impl<T> L2Union<T>
+where
+    T: Ord + Spill,
+{
+    fn union_and_sort_with_permutations_spilled(
+        left: Vec<T>,
+        right: Vec<T>,
+        order: &KeyOrder,
+        left_len: usize,
+        permutations: Vec<usize>,
+        settings: &SpillSort,
+    ) -> io::Result<Self> {
+        if order.presorted.is_some() {
+            return Ok(Self::union_and_sort_with_permutations(
+                left,
+                right,
+                order,
+                left_len,
+                permutations,
+            ));
+        }
+        let values = Self::union(left, right);
+        let values = sort_by(values, settings, |a, b| {
+            Self::compare(order, left_len, a, b)
+        })?;
+        Ok(Self::from_sorted(values, order, permutations))
+    }
+}
+
+/// Sort `values`, spilling sorted runs to disk if there are more values than
+/// the run length.
+fn sort_by<E, F>(mut values: Vec<E>, settings: &SpillSort, cmp: F) -> io::Result<Vec<E>>
+where
+    E: Spill,
+    F: Fn(&E, &E) -> Ordering,
+{
+    if values.len() <= settings.run_len {
+        values.sort_unstable_by(&cmp);
+        return Ok(values);
+    }
+
+    let mut runs = Vec::new();
+    let mut next_id = 0;
+    let mut iter = values.into_iter();
+    loop {
+        let mut chunk: Vec<_> = iter.by_ref().take(settings.run_len.max(1)).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        chunk.sort_unstable_by(&cmp);
+        runs.push(Run::write(&settings.dir, &mut next_id, chunk)?);
+    }
+    // The iterator still owns the unsorted buffer.
+    drop(iter);
+
+    merge(runs, &cmp)
+}
+
+fn merge<E, F>(runs: Vec<Run>, cmp: &F) -> io::Result<Vec<E>>
+where
+    E: Spill,
+    F: Fn(&E, &E) -> Ordering,
+{
+    let mut readers = Vec::with_capacity(runs.len());
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (idx, run) in runs.iter().enumerate() {
+        let mut reader = run.reader()?;
+        if let Some(value) = reader.next()? {
+            heap.push(Head {
+                value,
+                run: idx,
+                cmp,
+            });
+        }
+        readers.push(reader);
+    }
+
+    let mut out = Vec::new();
+    while let Some(Head { value, run, .. }) = heap.pop() {
+        out.push(value);
+        if let Some(value) = readers[run].next()? {
+            heap.push(Head { value, run, cmp });
+        }
+    }
+
+    Ok(out)
+}
+
+/// Next value to merge from a run.
+///
+/// Ordering is reversed so that `BinaryHeap` pops the smallest value first.
+/// Equal values pop in run order.
+struct Head<'a, E, F> {
+    value: E,
+    run: usize,
+    cmp: &'a F,
+}
+
+impl<E, F> Ord for Head<'_, E, F>
+where
+    F: Fn(&E, &E) -> Ordering,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp)(&other.value, &self.value).then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl<E, F> PartialOrd for Head<'_, E, F>
+where
+    F: Fn(&E, &E) -> Ordering,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<E, F> PartialEq for Head<'_, E, F>
+where
+    F: Fn(&E, &E) -> Ordering,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<E, F> Eq for Head<'_, E, F> where F: Fn(&E, &E) -> Ordering {}
+
+/// A sorted run spilled to a file. The file is removed on drop.
+struct Run {
+    path: PathBuf,
+    len: usize,
+}
+
+impl Run {
+    /// Write a run to the first free `iejoin-{pid}-{id}.run` in `dir`,
+    /// starting from `next_id`.
+    fn write<E: Spill>(dir: &Path, next_id: &mut usize, values: Vec<E>) -> io::Result<Run> {
+        // Never open an existing file, e.g. a symlink planted in a shared
+        // temp dir or a run of a concurrent sort, and try the next id instead.
+        let (path, file) = loop {
+            let id = *next_id;
+            *next_id += 1;
+            let path = dir.join(format!("iejoin-{}-{}.run", process::id(), id));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        let run = Run {
+            path,
+            len: values.len(),
+        };
+
+        let mut w = BufWriter::new(file);
+        for value in values.iter() {
+            value.spill(&mut w)?;
+        }
+        w.flush()?;
+
+        Ok(run)
+    }
+
+    fn reader(&self) -> io::Result<RunReader> {
+        Ok(RunReader {
+            reader: BufReader::new(File::open(&self.path)?),
+            remaining: self.len,
+        })
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+    remaining: usize,
+}
+
+impl RunReader {
+    fn next<E: Spill>(&mut self) -> io::Result<Option<E>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        E::unspill(&mut self.reader).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::CmpOp;
+
+    /// Empty directory for the runs of one test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("iejoin-test-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn spill_matches_in_memory() {
+        let dir = test_dir("spill_matches_in_memory");
+        let left1: Vec<i64> = (0..200).map(|i| (i * 37) % 101).collect();
+        let right1: Vec<i64> = (0..150).map(|i| (i * 53) % 97).collect();
+        let left2: Vec<i64> = (0..200).map(|i| (i * 17) % 89).collect();
+        let right2: Vec<i64> = (0..150).map(|i| (i * 29) % 83).collect();
+
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+                JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone()),
+            )
+        };
+
+        let (join1, join2) = join();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert!(!expected.is_empty());
+
+        let (join1, join2) = join();
+        let settings = SpillSort::new(16).with_dir(&dir);
+        let out: Vec<_> = InequalityJoin::new_spill_sorted(join1, join2, &settings)
+            .unwrap()
+            .collect();
+        assert_eq!(expected, out);
+
+        // Every run was removed.
+        assert_eq!(0, fs::read_dir(&dir).unwrap().count());
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn spill_string_keys() {
+        let dir = test_dir("spill_string_keys");
+        let words = |n: usize, seed: usize| -> Vec<String> {
+            (0..n).map(|i| format!("k{}", (i * seed) % 23)).collect()
+        };
+        let join = || {
+            (
+                JoinPredicate::new(CmpOp::LtEq, words(30, 7), words(20, 5)),
+                JoinPredicate::new(CmpOp::Gt, words(30, 3), words(20, 11)),
+            )
+        };
+
+        let (join1, join2) = join();
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+        assert!(!expected.is_empty());
+
+        let (join1, join2) = join();
+        let settings = SpillSort::new(8).with_dir(&dir);
+        let out: Vec<_> = InequalityJoin::new_spill_sorted(join1, join2, &settings)
+            .unwrap()
+            .collect();
+        assert_eq!(expected, out);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn run_skips_existing_file() {
+        let dir = test_dir("run_skips_existing_file");
+        let planted = dir.join(format!("iejoin-{}-0.run", process::id()));
+        fs::write(&planted, b"planted").unwrap();
+
+        let mut next_id = 0;
+        let run = Run::write(&dir, &mut next_id, vec![1u32, 2, 3]).unwrap();
+        assert_ne!(planted, run.path);
+        assert_eq!(2, next_id);
+        assert_eq!(b"planted", &fs::read(&planted).unwrap()[..]);
+
+        drop(run);
+        fs::remove_file(&planted).unwrap();
+        fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! Star joins of a fact table against two dimension tables.
+/// Combine two inequality joins that share the same left (fact) input.
+///
+/// Both joins are given as `(left_idx, right_idx)` matches, e.g. from
+/// [`InequalityJoin::into_indices`](super::InequalityJoin::into_indices).
+///
+/// Emits `(fact_idx, a_idx, b_idx)` for every fact row matching row `a_idx`
+/// of the first dimension and row `b_idx` of the second. Matches from the
+/// first join are buffered per fact row, matches from the second are streamed.
+#[derive(Debug)]
+pub struct StarInequalityJoin<I> {
+    /// Matching first dimension rows, indexed by fact row.
+    a_matches: Vec<Vec<usize>>,
+    b: I,
+
+    curr: Option<(usize, usize)>,
+    a_idx: usize,
+}
+
+impl<I> StarInequalityJoin<I>
+where
+    I: Iterator<Item = (usize, usize)>,
+{
+    pub fn new<A>(a: A, b: I) -> Self
+    where
+        A: IntoIterator<Item = (usize, usize)>,
+    {
+        let mut a_matches: Vec<Vec<usize>> = Vec::new();
+        for (fact, dim) in a {
+            if fact >= a_matches.len() {
+                a_matches.resize(fact + 1, Vec::new());
+            }
+            a_matches[fact].push(dim);
+        }
+
+        StarInequalityJoin {
+            a_matches,
+            b,
+            curr: None,
+            a_idx: 0,
+        }
+    }
+}
+
+impl<I> Iterator for StarInequalityJoin<I>
+where
+    I: Iterator<Item = (usize, usize)>,
+{
+    type Item = (usize, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((fact, b)) = self.curr {
+                if let Some(a) = self.a_matches.get(fact).and_then(|m| m.get(self.a_idx)) {
+                    self.a_idx += 1;
+                    return Some((fact, *a, b));
+                }
+            }
+
+            self.curr = Some(self.b.next()?);
+            self.a_idx = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+
+    #[test]
+    fn star() {
+        // Fact rows have a time and an amount. Dimension A holds time windows,
+        // dimension B holds amount bands.
+        let times = vec![5, 15, 25];
+        let amounts = vec![100, 250, 400];
+
+        let (a_start, a_end) = (vec![0, 10, 20], vec![10, 20, 30]);
+        let (b_low, b_high) = (vec![0, 200, 300], vec![300, 350, 500]);
+
+        let a = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::GtEq, times.clone(), a_start),
+            JoinPredicate::new(CmpOp::Lt, times, a_end),
+        );
+        let b = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::GtEq, amounts.clone(), b_low),
+            JoinPredicate::new(CmpOp::Lt, amounts, b_high),
+        );
+
+        let mut out: Vec<_> = StarInequalityJoin::new(a.into_indices(), b.into_indices()).collect();
+        out.sort();
+
+        let expected = vec![(0, 0, 0), (1, 1, 0), (1, 1, 1), (2, 2, 2)];
+        assert_eq!(expected, out);
+    }
+}
@@ -0,0 +1,91 @@
+//! Key ranges for pruning partitions that can't match.
+use super::{CmpOp, InequalityJoin, SortOrder};
+
+/// Smallest and largest key of each predicate for one side of a partition,
+/// see [`InequalityJoin::range_summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeSummary<T1, T2> {
+    key1: (T1, T1),
+    key2: (T2, T2),
+}
+
+impl<T1, T2> RangeSummary<T1, T2>
+where
+    T1: Ord,
+    T2: Ord,
+{
+    /// Whether a left row summarized by `self` could match a right row
+    /// summarized by `other` on `left1 op1 right1` and `left2 op2 right2`.
+    ///
+    /// Each predicate is checked on its own, so this can return true for
+    /// partitions without any matches, but never returns false for
+    /// partitions with matches.
+    pub fn can_match(&self, other: &RangeSummary<T1, T2>, op1: CmpOp, op2: CmpOp) -> bool {
+        can_hold(op1, &self.key1, &other.key1) && can_hold(op2, &self.key2, &other.key2)
+    }
+}
+
+/// Whether `left op right` holds for some left in `(min, max)` and right in
+/// `(min, max)`, by comparing the extremes most likely to satisfy it.
+fn can_hold<T: Ord>(op: CmpOp, left: &(T, T), right: &(T, T)) -> bool {
+    match op.sort_order() {
+        SortOrder::Asc => op.holds(&left.0, &right.1),
+        SortOrder::Desc => op.holds(&left.1, &right.0),
+    }
+}
+
+fn min_max<T: Ord + Clone>(keys: &[T]) -> Option<(T, T)> {
+    Some((keys.iter().min()?.clone(), keys.iter().max()?.clone()))
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone,
+    T2: Ord + Clone,
+{
+    /// Summarize the keys of one side of a partition, with `keys1` for the
+    /// first predicate and `keys2` for the second.
+    ///
+    /// Returns `None` if there are no keys.
+    pub fn range_summary(keys1: &[T1], keys2: &[T2]) -> Option<RangeSummary<T1, T2>> {
+        Some(RangeSummary {
+            key1: min_max(keys1)?,
+            key2: min_max(keys2)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::JoinPredicate;
+
+    #[test]
+    fn range_summary() {
+        let summary =
+            |k1: &[i32], k2: &[i32]| InequalityJoin::<i32, i32>::range_summary(k1, k2).unwrap();
+
+        let (left1, left2) = (vec![4, 1, 7], vec![2, 9, 4]);
+        let left = summary(&left1, &left2);
+        // Right partitions entirely below and above the left keys.
+        let (low1, low2) = (vec![-5, 0], vec![1, 3]);
+        let (high1, high2) = (vec![10, 12], vec![0, 1]);
+        let low = summary(&low1, &low2);
+        let high = summary(&high1, &high2);
+
+        assert!(!left.can_match(&low, CmpOp::Lt, CmpOp::GtEq));
+        assert!(left.can_match(&high, CmpOp::Lt, CmpOp::GtEq));
+
+        // Pruning agrees with running the joins.
+        for (right1, right2, summary) in [(&low1, &low2, &low), (&high1, &high2, &high)] {
+            let count = InequalityJoin::new(
+                JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone()),
+                JoinPredicate::new(CmpOp::GtEq, left2.clone(), right2.clone()),
+            )
+            .count();
+            assert_eq!(count > 0, left.can_match(summary, CmpOp::Lt, CmpOp::GtEq));
+        }
+
+        assert_eq!(None, InequalityJoin::<i32, i32>::range_summary(&[], &[1]));
+    }
+}
@@ -0,0 +1,124 @@
+//! Joins over keys from both sides interleaved in one vector.
+use super::{CmpOp, InequalityJoin, JoinScratch, KeyOrder, L1Union, L2Union, Side};
+use std::fmt::Debug;
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Debug,
+    T2: Ord + Debug,
+{
+    /// Create a join from keys tagged with the side they come from, with
+    /// `keys1` for the first predicate and `keys2` for the second.
+    ///
+    /// Rows are numbered within their side in the order they appear, so
+    /// matches are the same as joining the left and right keys split out into
+    /// separate vectors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys1` and `keys2` have different lengths or tag a position
+    /// with different sides.
+    pub fn from_tagged(
+        op1: CmpOp,
+        keys1: Vec<(T1, Side)>,
+        op2: CmpOp,
+        keys2: Vec<(T2, Side)>,
+    ) -> Self {
+        assert_eq!(keys1.len(), keys2.len(), "tagged key lengths differ");
+
+        let order1 = KeyOrder::new(op1);
+        let order2 = KeyOrder::new(op2);
+        let left_len = keys1.iter().filter(|(_, side)| side.is_left()).count();
+
+        let (mut left_idx, mut right_idx) = (0, 0);
+        let mut values1 = Vec::with_capacity(keys1.len());
+        let mut values2 = Vec::with_capacity(keys2.len());
+        for (pos, ((v1, s1), (v2, s2))) in keys1.into_iter().zip(keys2).enumerate() {
+            assert_eq!(s1, s2, "tagged sides differ at position {}", pos);
+            match s1 {
+                Side::Left => {
+                    values1.push((v1, Side::Left, left_idx));
+                    values2.push((v2, left_idx));
+                    left_idx += 1;
+                }
+                Side::Right => {
+                    values1.push((v1, Side::Right, right_idx));
+                    values2.push((v2, left_len + right_idx));
+                    right_idx += 1;
+                }
+            }
+        }
+
+        values1.sort_unstable_by(|a, b| L1Union::compare(&order1, a, b));
+        values2.sort_unstable_by(|a, b| L2Union::compare(&order2, left_len, a, b));
+        let (perms, l1) = L1Union::from_sorted(values1, &order1);
+        let l2 = L2Union::from_sorted(values2, &order2, perms);
+
+        Self::from_unions_with_scratch(l1, l2, JoinScratch::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iejoin::{CmpOp, InequalityJoin, JoinPredicate, Side};
+
+    #[test]
+    fn from_tagged() {
+        let (left1, left2) = (vec![4, 1, 7, 3], vec![2, 9, 4, 4]);
+        let (right1, right2) = (vec![5, 2, 8, 3], vec![1, 4, 6, 0]);
+        let sides = [
+            Side::Right,
+            Side::Left,
+            Side::Left,
+            Side::Right,
+            Side::Right,
+            Side::Left,
+            Side::Right,
+            Side::Left,
+        ];
+
+        let (mut l, mut r) = (0, 0);
+        let mut keys1 = Vec::new();
+        let mut keys2 = Vec::new();
+        for side in sides.iter() {
+            let (k1, k2) = match side {
+                Side::Left => {
+                    l += 1;
+                    (left1[l - 1], left2[l - 1])
+                }
+                Side::Right => {
+                    r += 1;
+                    (right1[r - 1], right2[r - 1])
+                }
+            };
+            keys1.push((k1, side.clone()));
+            keys2.push((k2, side.clone()));
+        }
+
+        let mut expected: Vec<_> = InequalityJoin::new(
+            JoinPredicate::new(CmpOp::Lt, left1, right1),
+            JoinPredicate::new(CmpOp::GtEq, left2, right2),
+        )
+        .into_indices()
+        .collect();
+        expected.sort_unstable();
+
+        let mut out: Vec<_> = InequalityJoin::from_tagged(CmpOp::Lt, keys1, CmpOp::GtEq, keys2)
+            .into_indices()
+            .collect();
+        out.sort_unstable();
+        assert!(!out.is_empty());
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_tagged_side_mismatch() {
+        InequalityJoin::from_tagged(
+            CmpOp::Lt,
+            vec![(1, Side::Left), (2, Side::Right)],
+            CmpOp::Lt,
+            vec![(1, Side::Right), (2, Side::Left)],
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Phase timings for profiling a join.
+use super::{InequalityJoin, JoinPredicate};
+use std::fmt::Debug;
+use std::time::Instant;
+
+/// Time spent in each phase of a join, in nanoseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Building and sorting the unions.
+    pub sort_ns: u128,
+    /// Scanning L2 to produce the matches.
+    pub scan_ns: u128,
+}
+
+impl<T1, T2> InequalityJoin<T1, T2>
+where
+    T1: Ord + Clone + Debug,
+    T2: Ord + Clone + Debug,
+{
+    /// Run the join to completion, recording how long the sort and scan phases
+    /// took.
+    pub fn timed(join1: JoinPredicate<T1>, join2: JoinPredicate<T2>) -> (Vec<(T1, T2)>, Timings) {
+        let start = Instant::now();
+        let join = InequalityJoin::new(join1, join2);
+        let sort_ns = start.elapsed().as_nanos();
+
+        let start = Instant::now();
+        let out: Vec<_> = join.collect();
+        let scan_ns = start.elapsed().as_nanos();
+
+        (out, Timings { sort_ns, scan_ns })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iejoin::CmpOp;
+
+    #[test]
+    fn timed() {
+        let left1: Vec<i64> = (0..500).map(|i| (i * 37) % 101).collect();
+        let right1: Vec<i64> = (0..500).map(|i| (i * 53) % 97).collect();
+        let left2: Vec<i64> = (0..500).map(|i| (i * 17) % 89).collect();
+        let right2: Vec<i64> = (0..500).map(|i| (i * 29) % 83).collect();
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, left1.clone(), right1.clone());
+        let join2 = JoinPredicate::new(CmpOp::Gt, left2.clone(), right2.clone());
+        let expected: Vec<_> = InequalityJoin::new(join1, join2).collect();
+
+        let join1 = JoinPredicate::new(CmpOp::Lt, left1, right1);
+        let join2 = JoinPredicate::new(CmpOp::Gt, left2, right2);
+        let (out, timings) = InequalityJoin::timed(join1, join2);
+
+        assert_eq!(expected, out);
+        assert!(timings.sort_ns > 0);
+        assert!(timings.scan_ns > 0);
+    }
+}
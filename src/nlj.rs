@@ -1,11 +1,54 @@
 //! Nested loop join.
-use std::iter::Peekable;
+use bitvec::vec::BitVec;
 
 pub struct NestedLoopJoin<L: Iterator, R, F> {
-    left: Peekable<L>,
+    left: L,
     right: R,
-    right_curr: R,
     pred: F,
+
+    /// Left item being joined from the front, and the remaining right items
+    /// to check it against.
+    front: Option<(L::Item, R)>,
+    /// Left item being joined from the back, checked against the right items
+    /// in reverse.
+    back: Option<(L::Item, R)>,
+
+    max_output: Option<usize>,
+    emitted: usize,
+    exceeded: bool,
+}
+
+impl<L, R> NestedLoopJoin<L, R, fn(&L::Item, &R::Item) -> bool>
+where
+    L: Iterator,
+    L::Item: Clone,
+    R: Iterator + Clone,
+{
+    /// Every pair of left and right items, without evaluating a predicate.
+    pub fn cross(left: L, right: R) -> impl Iterator<Item = (L::Item, R::Item)> {
+        left.flat_map(move |l| right.clone().map(move |r| (l.clone(), r)))
+    }
+}
+
+impl<L, R, F> NestedLoopJoin<L, R, F>
+where
+    L: Iterator,
+{
+    /// Create a join with a predicate that can fail, see
+    /// [`TryNestedLoopJoin`].
+    pub fn try_new<E>(left: L, right: R, pred: F) -> TryNestedLoopJoin<L, R, F>
+    where
+        R: Iterator + Clone,
+        F: Fn(&L::Item, &R::Item) -> Result<bool, E>,
+    {
+        TryNestedLoopJoin {
+            left,
+            right,
+            pred,
+            curr: None,
+            failed: false,
+        }
+    }
 }
 
 impl<L, R, F> NestedLoopJoin<L, R, F>
@@ -15,14 +58,49 @@ where
     F: Fn(&L::Item, &R::Item) -> bool,
 {
     pub fn new(left: L, right: R, pred: F) -> Self {
-        let right_curr = right.clone();
         NestedLoopJoin {
-            left: left.peekable(),
+            left,
             right,
-            right_curr,
             pred,
+            front: None,
+            back: None,
+            max_output: None,
+            emitted: 0,
+            exceeded: false,
         }
     }
+
+    /// Stop once `n` pairs have been produced if the join would produce more,
+    /// see [`NestedLoopJoin::exceeded`].
+    pub fn with_max_output(mut self, n: usize) -> Self {
+        self.max_output = Some(n);
+        self
+    }
+
+    /// Whether the join stopped early because it would have produced more
+    /// pairs than the maximum output.
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// Evaluate the predicate for every pair, returning one row per left item
+    /// with bit `j` set if the item matches the `j`th right item.
+    pub fn match_matrix(left: L, right: R, pred: F) -> Vec<BitVec> {
+        let right: Vec<_> = right.collect();
+        left.map(|l| right.iter().map(|r| pred(&l, r)).collect())
+            .collect()
+    }
+
+    /// Apply the output cap to a pair found from either end.
+    fn limit(&mut self, pair: Option<(L::Item, R::Item)>) -> Option<(L::Item, R::Item)> {
+        let pair = pair?;
+        if self.max_output.is_some_and(|max| self.emitted >= max) {
+            self.exceeded = true;
+            return None;
+        }
+        self.emitted += 1;
+        Some(pair)
+    }
 }
 
 impl<LI, L, R, F> Iterator for NestedLoopJoin<L, R, F>
@@ -35,20 +113,140 @@ where
     type Item = (L::Item, R::Item);
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.exceeded {
+            return None;
+        }
+        let pair = self.next_pair();
+        self.limit(pair)
+    }
+}
+
+impl<LI, L, R, F> DoubleEndedIterator for NestedLoopJoin<L, R, F>
+where
+    LI: Clone,
+    L: DoubleEndedIterator<Item = LI>,
+    R: DoubleEndedIterator + Clone,
+    F: Fn(&L::Item, &R::Item) -> bool,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.exceeded {
+            return None;
+        }
+        let pair = self.next_back_pair();
+        self.limit(pair)
+    }
+}
+
+impl<LI, L, R, F> NestedLoopJoin<L, R, F>
+where
+    LI: Clone,
+    L: Iterator<Item = LI>,
+    R: Iterator + Clone,
+    F: Fn(&L::Item, &R::Item) -> bool,
+{
+    fn next_pair(&mut self) -> Option<(L::Item, R::Item)> {
         loop {
-            let left = match self.left.peek() {
-                Some(item) => item,
-                None => return None,
-            };
-
-            while let Some(right) = self.right_curr.next() {
-                if (self.pred)(left, &right) {
-                    return Some((left.clone(), right));
+            if let Some((left, right_curr)) = &mut self.front {
+                for right in right_curr.by_ref() {
+                    if (self.pred)(left, &right) {
+                        return Some((left.clone(), right));
+                    }
                 }
+                self.front = None;
             }
 
-            let _ = self.left.next();
-            self.right_curr = self.right.clone();
+            match self.left.next() {
+                Some(left) => self.front = Some((left, self.right.clone())),
+                None => {
+                    // Only the back's left item is left. Finish its remaining
+                    // right items, which the back hasn't reached yet.
+                    let (left, right_curr) = self.back.as_mut()?;
+                    for right in right_curr.by_ref() {
+                        if (self.pred)(left, &right) {
+                            return Some((left.clone(), right));
+                        }
+                    }
+                    self.back = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<LI, L, R, F> NestedLoopJoin<L, R, F>
+where
+    LI: Clone,
+    L: DoubleEndedIterator<Item = LI>,
+    R: DoubleEndedIterator + Clone,
+    F: Fn(&L::Item, &R::Item) -> bool,
+{
+    fn next_back_pair(&mut self) -> Option<(L::Item, R::Item)> {
+        loop {
+            if let Some((left, right_curr)) = &mut self.back {
+                while let Some(right) = right_curr.next_back() {
+                    if (self.pred)(left, &right) {
+                        return Some((left.clone(), right));
+                    }
+                }
+                self.back = None;
+            }
+
+            match self.left.next_back() {
+                Some(left) => self.back = Some((left, self.right.clone())),
+                None => {
+                    let (left, right_curr) = self.front.as_mut()?;
+                    while let Some(right) = right_curr.next_back() {
+                        if (self.pred)(left, &right) {
+                            return Some((left.clone(), right));
+                        }
+                    }
+                    self.front = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Nested loop join with a fallible predicate, yielding `Err` for the first
+/// pair the predicate fails on and nothing after it.
+pub struct TryNestedLoopJoin<L: Iterator, R, F> {
+    left: L,
+    right: R,
+    pred: F,
+
+    curr: Option<(L::Item, R)>,
+    failed: bool,
+}
+
+impl<LI, L, R, F, E> Iterator for TryNestedLoopJoin<L, R, F>
+where
+    LI: Clone,
+    L: Iterator<Item = LI>,
+    R: Iterator + Clone,
+    F: Fn(&L::Item, &R::Item) -> Result<bool, E>,
+{
+    type Item = Result<(L::Item, R::Item), E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            if let Some((left, right_curr)) = &mut self.curr {
+                for right in right_curr.by_ref() {
+                    match (self.pred)(left, &right) {
+                        Ok(true) => return Some(Ok((left.clone(), right))),
+                        Ok(false) => (),
+                        Err(e) => {
+                            self.failed = true;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+            }
+            self.curr = Some((self.left.next()?, self.right.clone()));
         }
     }
 }
@@ -71,6 +269,23 @@ mod tests {
         assert_eq!(expected, out);
     }
 
+    #[test]
+    fn equality_match_matrix() {
+        let pred = |a: &i32, b: &i32| a == b;
+
+        let l = vec![1, 2, 3, 4, 5];
+        let r = vec![2, 3, 4, 5, 6];
+
+        let matrix = NestedLoopJoin::match_matrix(l.into_iter(), r.into_iter(), pred);
+        assert_eq!(5, matrix.len());
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(5, row.len());
+            let ones: Vec<_> = row.iter_ones().collect();
+            let expected: Vec<_> = i.checked_sub(1).into_iter().collect();
+            assert_eq!(expected, ones);
+        }
+    }
+
     #[test]
     fn fixed_ranges() {
         let pred = |&a: &i32, &b: &i32| b >= a - 1 && b <= a + 1;
@@ -95,6 +310,90 @@ mod tests {
         assert_eq!(expected, out);
     }
 
+    #[test]
+    fn max_output() {
+        let pred = |a: &i32, b: &i32| a != b;
+
+        let l = vec![1, 2, 3, 4];
+        let r = vec![1, 2, 3, 4];
+
+        let mut nlj = NestedLoopJoin::new(l.clone().into_iter(), r.clone().into_iter(), pred)
+            .with_max_output(5);
+        let out: Vec<_> = nlj.by_ref().collect();
+        assert_eq!(vec![(1, 2), (1, 3), (1, 4), (2, 1), (2, 3)], out);
+        assert!(nlj.exceeded());
+        assert_eq!(None, nlj.next());
+
+        // Exactly at the limit isn't exceeding it.
+        let mut nlj = NestedLoopJoin::new(l.into_iter(), r.into_iter(), pred).with_max_output(12);
+        assert_eq!(12, nlj.by_ref().count());
+        assert!(!nlj.exceeded());
+    }
+
+    #[test]
+    fn double_ended() {
+        let pred = |a: &i32, b: &i32| a > b;
+
+        let l = vec![4, 3, 4, 1, 6];
+        let r = vec![1, 5, 2, 3];
+        let forward: Vec<_> =
+            NestedLoopJoin::new(l.clone().into_iter(), r.clone().into_iter(), pred).collect();
+
+        let backward: Vec<_> =
+            NestedLoopJoin::new(l.clone().into_iter(), r.clone().into_iter(), pred)
+                .rev()
+                .collect();
+        assert_eq!(forward.iter().rev().cloned().collect::<Vec<_>>(), backward);
+
+        // Alternate ends, meeting part way through a left item.
+        for take_front in 0..=forward.len() {
+            let mut nlj = NestedLoopJoin::new(l.clone().into_iter(), r.clone().into_iter(), pred);
+            let mut front: Vec<_> = nlj.by_ref().take(take_front).collect();
+            let mut back: Vec<_> = nlj.by_ref().rev().collect();
+            back.reverse();
+            front.extend(back);
+            assert_eq!(forward, front, "take_front: {}", take_front);
+        }
+
+        let mut nlj = NestedLoopJoin::new(l.into_iter(), r.into_iter(), pred);
+        let mut out = Vec::new();
+        loop {
+            match (nlj.next(), nlj.next_back()) {
+                (None, None) => break,
+                (a, b) => out.extend(a.into_iter().chain(b)),
+            }
+        }
+        out.sort_unstable();
+        let mut expected = forward;
+        expected.sort_unstable();
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn cross() {
+        let l = vec![1, 2, 3];
+        let r = vec!['a', 'b'];
+        let out: Vec<_> = NestedLoopJoin::cross(l.into_iter(), r.into_iter()).collect();
+
+        let expected = vec![(1, 'a'), (1, 'b'), (2, 'a'), (2, 'b'), (3, 'a'), (3, 'b')];
+        assert_eq!(expected, out);
+    }
+
+    #[test]
+    fn try_new() {
+        let pred = |a: &&str, b: &i32| a.parse::<i32>().map(|a| a < *b);
+
+        let l = vec!["1", "3", "x", "0"];
+        let r = vec![2, 4];
+        let out: Vec<_> = NestedLoopJoin::try_new(l.into_iter(), r.into_iter(), pred).collect();
+
+        assert_eq!(4, out.len());
+        assert_eq!(Ok(("1", 2)), out[0]);
+        assert_eq!(Ok(("1", 4)), out[1]);
+        assert_eq!(Ok(("3", 4)), out[2]);
+        assert!(out[3].is_err());
+    }
+
     #[test]
     fn out_of_order() {
         let pred = |a: &i32, b: &i32| a > b;
@@ -0,0 +1,51 @@
+//! Allocations of `InequalityJoin::conjunction_array`.
+//!
+//! Kept in its own test binary, with a single test, so that no other test
+//! allocates while the counter is read.
+use iejoins::iejoin::{CmpOp, InequalityJoin, JoinPredicate};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn predicates<const N: usize>() -> [JoinPredicate<i32>; N] {
+    let ops = [CmpOp::Lt, CmpOp::GtEq, CmpOp::LtEq, CmpOp::Gt];
+    std::array::from_fn(|i| {
+        let left = (0..64).map(|l| (l * (i as i32 + 3)) % 17).collect();
+        let right = (0..48).map(|r| (r * (i as i32 + 5)) % 13).collect();
+        JoinPredicate::new(ops[i % ops.len()], left, right)
+    })
+}
+
+/// Allocations made joining `predicates`, along with the number of matches.
+fn count_allocs<const N: usize>(predicates: [JoinPredicate<i32>; N]) -> (usize, usize) {
+    let before = ALLOCS.load(Ordering::Relaxed);
+    let matches = InequalityJoin::conjunction_array(predicates).count();
+    (ALLOCS.load(Ordering::Relaxed) - before, matches)
+}
+
+#[test]
+fn residuals_not_allocated() {
+    let (two, all) = count_allocs(predicates::<2>());
+    let (five, matches) = count_allocs(predicates::<5>());
+    assert!(matches < all);
+
+    // Only the first two predicates are sorted, the residuals add nothing.
+    assert_eq!(two, five);
+}